@@ -0,0 +1,17 @@
+// The PDF writer embeds the fonts through the FreeType font backend of
+// cairo, which cairo-rs does not bind: check that the system cairo was
+// built with it, and link to it and to FreeType
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_PDF").is_none() {
+        return;
+    }
+
+    for library in &["cairo-ft", "freetype2"] {
+        if let Err(e) = pkg_config::Config::new().probe(library) {
+            panic!(
+                "The pdf feature requires cairo built with FreeType support: {}",
+                e
+            );
+        }
+    }
+}
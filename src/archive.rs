@@ -1,21 +1,22 @@
 use log::debug;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
 
 use crate::error::Result;
 
-#[derive(Debug)]
-pub struct Archive {
-    zip: zip::ZipArchive<File>,
-}
+/// A reader of an archive, as a trait object
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<R: Read + Seek + Send> ReadSeek for R {}
 
-impl Archive {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(path.as_ref())?;
+pub struct Archive<R> {
+    zip: zip::ZipArchive<R>,
+}
 
+impl<R: Read + Seek> Archive<R> {
+    pub fn new(reader: R) -> Result<Self> {
         Ok(Archive {
-            zip: zip::ZipArchive::new(file)?,
+            zip: zip::ZipArchive::new(reader)?,
         })
     }
 
@@ -49,3 +50,80 @@ impl Archive {
         Ok(res)
     }
 }
+
+impl<R: Read + Seek> std::fmt::Debug for Archive<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archive")
+            .field("len", &self.zip.len())
+            .finish()
+    }
+}
+
+/// Resolves a part URI, either absolute or relative to the part `base`,
+/// to the name of the corresponding item in the archive. Fragments are
+/// discarded.
+pub fn resolve_part_name<P: AsRef<Path>>(base: P, uri: &str) -> PathBuf {
+    let uri = uri.split('#').next().unwrap_or_default();
+
+    let mut res = PathBuf::new();
+
+    if !uri.starts_with('/') {
+        if let Some(dir) = base.as_ref().parent() {
+            res.push(dir);
+        }
+    }
+
+    for c in uri.split('/') {
+        match c {
+            "" | "." => (),
+            ".." => {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Resolves a hyperlink URI like `resolve_part_name`, keeping the
+/// fragment. A URI with only a fragment points inside `base` itself,
+/// URIs with a scheme point outside of the package and are returned
+/// unchanged.
+pub fn resolve_uri<P: AsRef<Path>>(base: P, uri: &str) -> String {
+    if has_scheme(uri) {
+        return String::from(uri);
+    }
+
+    let (path, fragment) = match uri.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (uri, None),
+    };
+
+    let mut res = if path.is_empty() {
+        base.as_ref().display().to_string()
+    } else {
+        resolve_part_name(base, path).display().to_string()
+    };
+
+    if let Some(fragment) = fragment {
+        res.push('#');
+        res.push_str(fragment);
+    }
+
+    res
+}
+
+/// Returns true if the URI starts with a scheme, e.g. "http:"
+pub fn has_scheme(uri: &str) -> bool {
+    match uri.find(':') {
+        Some(i) => {
+            let scheme = &uri[..i];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
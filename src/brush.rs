@@ -0,0 +1,265 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::geometry::Point;
+use crate::image::Image;
+use crate::renderer::{Rect, RenderNode, RenderTransform};
+
+#[derive(Clone, Debug)]
+pub(crate) enum Brush {
+    Image(ImageBrush),
+    LinearGradient(LinearGradientBrush),
+    RadialGradient(RadialGradientBrush),
+    SolidColor(SolidColorBrush),
+    Visual(VisualBrush),
+}
+
+impl Brush {
+    /// Returns the colors of a solid color or gradient brush
+    pub fn colors_mut(&mut self) -> Vec<&mut Color> {
+        match self {
+            Brush::SolidColor(b) => vec![&mut b.color],
+            Brush::LinearGradient(LinearGradientBrush { gradient, .. })
+            | Brush::RadialGradient(RadialGradientBrush { gradient, .. }) => gradient
+                .gradient_stops
+                .iter_mut()
+                .map(|stop| &mut stop.color)
+                .collect(),
+            Brush::Image(_) | Brush::Visual(_) => Vec::new(),
+        }
+    }
+}
+
+/// The abbreviated syntax of a brush is a solid color
+impl FromStr for Brush {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Color>().map(|color| {
+            Brush::SolidColor(SolidColorBrush {
+                opacity: 1.0,
+                color,
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SolidColorBrush {
+    pub opacity: f64,
+    pub color: Color,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum MappingMode {
+    #[default]
+    Absolute,
+    RelativeToBoundingBox,
+}
+
+impl FromStr for MappingMode {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Absolute" => Ok(MappingMode::Absolute),
+            "RelativeToBoundingBox" => Ok(MappingMode::RelativeToBoundingBox),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum SpreadMethod {
+    #[default]
+    Pad,
+    Reflect,
+    Repeat,
+}
+
+impl FromStr for SpreadMethod {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pad" => Ok(SpreadMethod::Pad),
+            "Reflect" => Ok(SpreadMethod::Reflect),
+            "Repeat" => Ok(SpreadMethod::Repeat),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum ColorInterpolationMode {
+    ScRgbLinearInterpolation,
+    #[default]
+    SRgbLinearInterpolation,
+}
+
+impl FromStr for ColorInterpolationMode {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ScRgbLinearInterpolation" => Ok(ColorInterpolationMode::ScRgbLinearInterpolation),
+            "SRgbLinearInterpolation" => Ok(ColorInterpolationMode::SRgbLinearInterpolation),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct GradientStop {
+    pub color: Color,
+    pub offset: f64,
+}
+
+/// Properties shared by linear and radial gradients
+#[derive(Clone, Debug)]
+pub(crate) struct Gradient {
+    pub opacity: f64,
+    pub color_interpolation_mode: ColorInterpolationMode,
+    pub spread_method: SpreadMethod,
+    pub mapping_mode: MappingMode,
+    pub transform: Option<RenderTransform>,
+    pub gradient_stops: Vec<GradientStop>,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            color_interpolation_mode: ColorInterpolationMode::default(),
+            spread_method: SpreadMethod::default(),
+            mapping_mode: MappingMode::default(),
+            transform: None,
+            gradient_stops: Vec::new(),
+        }
+    }
+}
+
+// Number of stops added between each pair of gradient stops to
+// approximate the interpolation in scRGB, since renderers interpolate
+// colors in sRGB
+const SC_RGB_INTERPOLATION_STEPS: u32 = 16;
+
+impl Gradient {
+    /// Returns the (offset, color) stops to interpolate in sRGB, with
+    /// intermediate stops when the gradient is interpolated in scRGB
+    pub fn color_stops(&self) -> Vec<(f64, Color)> {
+        let stops = &self.gradient_stops;
+
+        match self.color_interpolation_mode {
+            ColorInterpolationMode::SRgbLinearInterpolation => stops
+                .iter()
+                .map(|stop| (stop.offset, stop.color.clone()))
+                .collect(),
+            ColorInterpolationMode::ScRgbLinearInterpolation => {
+                let mut res = Vec::new();
+
+                if let Some(first) = stops.first() {
+                    res.push((first.offset, first.color.clone()));
+                }
+
+                for w in stops.windows(2) {
+                    let (s0, s1) = (&w[0], &w[1]);
+
+                    for i in 1..=SC_RGB_INTERPOLATION_STEPS {
+                        let t = f64::from(i) / f64::from(SC_RGB_INTERPOLATION_STEPS);
+                        let offset = s0.offset + (s1.offset - s0.offset) * t;
+                        res.push((offset, s0.color.interpolate_sc_rgb(&s1.color, t)));
+                    }
+                }
+
+                res
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LinearGradientBrush {
+    pub gradient: Gradient,
+    pub start_point: Point,
+    pub end_point: Point,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RadialGradientBrush {
+    pub gradient: Gradient,
+    pub center: Point,
+    pub gradient_origin: Point,
+    pub radius_x: f64,
+    pub radius_y: f64,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum TileMode {
+    #[default]
+    None,
+    Tile,
+    FlipX,
+    FlipY,
+    FlipXY,
+}
+
+impl FromStr for TileMode {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "None" => Ok(TileMode::None),
+            "Tile" => Ok(TileMode::Tile),
+            "FlipX" => Ok(TileMode::FlipX),
+            "FlipY" => Ok(TileMode::FlipY),
+            "FlipXY" => Ok(TileMode::FlipXY),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Properties shared by image and visual brushes. The viewbox is the
+/// portion of the brush content that is mapped to the viewport, the
+/// viewport is the position of the first tile. XPS only allows absolute
+/// units for both, the viewbox of an image is in 1/96 inch.
+#[derive(Clone, Debug)]
+pub(crate) struct TileBrush {
+    pub opacity: f64,
+    pub transform: Option<RenderTransform>,
+    pub viewbox: Rect,
+    pub viewbox_units: MappingMode,
+    pub viewport: Rect,
+    pub viewport_units: MappingMode,
+    pub tile_mode: TileMode,
+}
+
+impl Default for TileBrush {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            transform: None,
+            viewbox: Rect::default(),
+            viewbox_units: MappingMode::default(),
+            viewport: Rect::default(),
+            viewport_units: MappingMode::default(),
+            tile_mode: TileMode::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ImageBrush {
+    pub tile: TileBrush,
+    pub image_source: String, // FIXME: use a type for URI
+    pub image: Option<Arc<Image>>,
+}
+
+/// The content of a visual brush is a tree of render nodes, with
+/// coordinates in the viewbox space
+#[derive(Clone, Debug, Default)]
+pub(crate) struct VisualBrush {
+    pub tile: TileBrush,
+    pub visual: Vec<RenderNode>,
+}
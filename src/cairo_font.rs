@@ -0,0 +1,91 @@
+use std::os::raw::{c_int, c_long, c_void};
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::font::Font;
+
+type FtLibrary = *mut c_void;
+type FtFace = *mut c_void;
+
+// FreeType and the cairo FreeType functions are linked by the build
+// script, which checks that the system cairo provides them
+extern "C" {
+    fn FT_Init_FreeType(library: *mut FtLibrary) -> c_int;
+    fn FT_Done_FreeType(library: FtLibrary) -> c_int;
+    fn FT_New_Memory_Face(
+        library: FtLibrary,
+        data: *const u8,
+        size: c_long,
+        index: c_long,
+        face: *mut FtFace,
+    ) -> c_int;
+}
+
+// Cairo functions not bound by cairo-rs
+extern "C" {
+    fn cairo_ft_font_face_create_for_ft_face(face: FtFace, load_flags: c_int) -> *mut c_void;
+    fn cairo_font_face_status(font_face: *mut c_void) -> c_int;
+}
+
+// The FreeType library of a face, with the font data the face
+// reads from. Attached to the cairo font face, so that they are
+// freed when cairo destroys it.
+struct FaceData {
+    library: FtLibrary,
+    _font: Arc<Font>,
+}
+
+impl Drop for FaceData {
+    fn drop(&mut self) {
+        // Also frees the face
+        unsafe {
+            FT_Done_FreeType(self.library);
+        }
+    }
+}
+
+static FACE_DATA_KEY: cairo::UserDataKey<FaceData> = cairo::UserDataKey::new();
+
+/// Returns a cairo font face for the font, through FreeType, so that
+/// PDF surfaces can embed subsets of it
+pub(crate) fn font_face(font: &Arc<Font>) -> Option<cairo::FontFace> {
+    let data = font.data();
+
+    unsafe {
+        let mut library = ptr::null_mut();
+        if FT_Init_FreeType(&mut library) != 0 {
+            return None;
+        }
+
+        let mut face = ptr::null_mut();
+        if FT_New_Memory_Face(
+            library,
+            data.as_ptr(),
+            data.len() as c_long,
+            c_long::from(font.index()),
+            &mut face,
+        ) != 0
+        {
+            FT_Done_FreeType(library);
+            return None;
+        }
+
+        let face_data = Rc::new(FaceData {
+            library,
+            _font: font.clone(),
+        });
+
+        let raw = cairo_ft_font_face_create_for_ft_face(face, 0);
+        let font_face = cairo::FontFace::from_raw_full(raw as *mut _);
+
+        // On errors, the library is freed after the font face
+        if cairo_font_face_status(raw) != 0 {
+            return None;
+        }
+
+        font_face.set_user_data(&FACE_DATA_KEY, face_data);
+
+        Some(font_face)
+    }
+}
@@ -1,35 +1,752 @@
-use crate::renderer::{Canvas, Glyphs, Path, RenderTransform, Renderer};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+#[cfg(feature = "pdf")]
+use std::sync::Arc;
 
-use crate::error::RenderResult;
+use crate::archive::has_scheme;
+use crate::brush::{Brush, Gradient, MappingMode, SpreadMethod, TileBrush, TileMode};
+#[cfg(feature = "pdf")]
+use crate::cairo_font;
+use crate::font;
+#[cfg(feature = "pdf")]
+use crate::font::Font;
+use crate::geometry::{FillRule, Geometry, PathBuilder, Point};
+use crate::image::Image;
+use crate::renderer::{
+    Canvas, Clip, Glyphs, LineCap, LineJoin, NavigateUri, Opacity, OpacityMask, Path,
+    RenderTransform, Renderer,
+};
+
+use crate::error::{RenderError, RenderResult};
 
 /// Cairo renderer
 #[derive(Debug)]
 pub struct CairoRenderer {
     cr: cairo::Context,
+    destinations: RefCell<Destinations>,
+    in_link: Cell<bool>,
+
+    // Only used when rendering to PDF, to embed the fonts instead
+    // of drawing the outlines of the glyphs
+    #[cfg(feature = "pdf")]
+    font_faces: Option<RefCell<FontFaces>>,
+}
+
+// The cairo font faces of the fonts of the package, by font and
+// bold simulation
+#[cfg(feature = "pdf")]
+type FontFaces = Vec<(Arc<Font>, bool, Option<cairo::FontFace>)>;
+
+// The named destinations of the document, only known when
+// rendering to PDF. Cairo fails if a link points to a destination
+// that does not exist, so only the links to these are tagged.
+#[derive(Debug, Default)]
+pub(crate) struct Destinations {
+    pub names: HashSet<String>,
+    // Link targets of the current page that were not drawn yet
+    pub pending: HashSet<String>,
 }
 
 impl CairoRenderer {
     /// Returns a renderer for the given cairo context
     pub fn new(cr: cairo::Context) -> Self {
-        Self { cr }
+        Self {
+            cr,
+            destinations: RefCell::new(Destinations::default()),
+            in_link: Cell::new(false),
+            #[cfg(feature = "pdf")]
+            font_faces: None,
+        }
+    }
+
+    // Returns a renderer drawing text with the fonts of the package,
+    // so that PDF surfaces embed them and the text can be selected
+    #[cfg(feature = "pdf")]
+    pub(crate) fn with_font_faces(cr: cairo::Context) -> Self {
+        Self {
+            font_faces: Some(RefCell::default()),
+            ..Self::new(cr)
+        }
+    }
+
+    #[cfg(feature = "pdf")]
+    pub(crate) fn destinations(&self) -> std::cell::RefMut<'_, Destinations> {
+        self.destinations.borrow_mut()
+    }
+
+    #[cfg(feature = "pdf")]
+    fn font_face(&self, font: &Arc<Font>, bold: bool) -> Option<cairo::FontFace> {
+        let mut font_faces = self.font_faces.as_ref()?.borrow_mut();
+
+        if let Some((_, _, face)) = font_faces
+            .iter()
+            .find(|(f, b, _)| Arc::ptr_eq(f, font) && *b == bold)
+        {
+            return face.clone();
+        }
+
+        let face = cairo_font::font_face(font);
+        if let (Some(face), true) = (&face, bold) {
+            face.set_synthesize(cairo::FtSynthesize::Bold);
+        }
+
+        font_faces.push((font.clone(), bold, face.clone()));
+
+        face
+    }
+
+    // Draws the glyphs with the font face of their font, mapping them
+    // to the UnicodeString. Returns false if there is no font face.
+    #[cfg(feature = "pdf")]
+    fn show_glyphs(&self, glyphs: &Glyphs) -> bool {
+        let font = match glyphs.font {
+            Some(ref font) => font,
+            None => return false,
+        };
+
+        let bold = font::bold_stroke_width(glyphs).is_some();
+        let face = match self.font_face(font, bold) {
+            Some(face) => face,
+            None => return false,
+        };
+
+        let em = glyphs.font_rendering_em_size;
+        let skew = font::italic_skew(glyphs);
+
+        self.cr.set_font_face(&face);
+        self.cr
+            .set_font_matrix(cairo::Matrix::new(em, 0.0, -skew * em, em, 0.0, 0.0));
+
+        let placed = font::layout(glyphs, font)
+            .iter()
+            .map(|g| cairo::Glyph {
+                index: g.id.into(),
+                x: g.origin.x,
+                y: g.origin.y,
+            })
+            .collect::<Vec<_>>();
+
+        let clusters = font::text_clusters(glyphs, font);
+        let text = glyphs.unicode_string.as_ref().map_or("", |s| &s.0[..]);
+
+        // Cairo fails on clusters that do not cover the text and the glyphs
+        if !text.is_empty()
+            && clusters.iter().map(|c| c.0).sum::<usize>() == text.len()
+            && clusters.iter().map(|c| c.1).sum::<usize>() == placed.len()
+        {
+            let clusters = clusters
+                .iter()
+                .map(|&(bytes, glyphs)| cairo::TextCluster {
+                    num_bytes: bytes as i32,
+                    num_glyphs: glyphs as i32,
+                })
+                .collect::<Vec<_>>();
+
+            self.cr
+                .show_text_glyphs(text, &placed, &clusters, cairo::TextClusterFlags::None);
+        } else {
+            self.cr.show_glyphs(&placed);
+        }
+
+        true
+    }
+
+    // Tags the drawing of an element as a hyperlink and as the
+    // destination of the link target with its name. The tags are
+    // only used by PDF surfaces. Nested links are ignored.
+    fn with_tags<F>(
+        &self,
+        name: &Option<String>,
+        navigate_uri: &Option<NavigateUri>,
+        f: F,
+    ) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let dest = name
+            .as_ref()
+            .filter(|n| self.destinations.borrow_mut().pending.remove(*n));
+
+        let link = match navigate_uri {
+            Some(uri) if !self.in_link.get() => self.link_attributes(&uri.0),
+            _ => None,
+        };
+
+        if let Some(name) = dest {
+            let attributes = format!("name={}", quote_attribute(name));
+            self.cr.tag_begin(cairo::CAIRO_TAG_DEST, &attributes);
+        }
+
+        if let Some(ref attributes) = link {
+            self.cr.tag_begin(cairo::CAIRO_TAG_LINK, attributes);
+            self.in_link.set(true);
+        }
+
+        let res = f();
+
+        if link.is_some() {
+            self.in_link.set(false);
+            self.cr.tag_end(cairo::CAIRO_TAG_LINK);
+        }
+
+        if dest.is_some() {
+            self.cr.tag_end(cairo::CAIRO_TAG_DEST);
+        }
+
+        res
+    }
+
+    fn link_attributes(&self, uri: &str) -> Option<String> {
+        if has_scheme(uri) {
+            return Some(format!("uri={}", quote_attribute(uri)));
+        }
+
+        let dest = destination_name(uri);
+        if self.destinations.borrow().names.contains(&dest) {
+            Some(format!("dest={}", quote_attribute(&dest)))
+        } else {
+            None
+        }
+    }
+
+    // Wraps the rendering of an element in save/restore, so that
+    // its transform and other state does not leak to its siblings
+    fn with_saved_state<F>(&self, f: F) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        self.cr.save();
+        let res = f();
+        self.cr.restore();
+
+        res?;
+
+        match self.cr.status() {
+            cairo::Status::Success => Ok(()),
+            status => Err(RenderError::Cairo(status)),
+        }
+    }
+
+    fn set_geometry(&self, geometry: &Geometry, stroke: bool) {
+        self.cr.new_path();
+
+        // The geometry transform only affects the coordinates of
+        // the path, not the stroke
+        let matrix = self.cr.get_matrix();
+        if let Some(t) = geometry.transform {
+            self.cr.transform(t.into());
+        }
+
+        let mut builder = CairoPathBuilder(&self.cr);
+        if stroke {
+            geometry.build_stroke(&mut builder);
+        } else {
+            geometry.build_fill(&mut builder);
+        }
+
+        self.cr.set_matrix(matrix);
+    }
+
+    // Sets the brush as the source for the current path, returns
+    // false if the brush is not supported or does not paint anything
+    fn set_source_brush(&self, brush: &Brush) -> RenderResult<bool> {
+        match brush {
+            Brush::SolidColor(b) => {
+                let (r, g, bl, a) = b.color.to_rgba();
+                self.cr.set_source_rgba(r, g, bl, a * b.opacity);
+                Ok(true)
+            }
+            Brush::LinearGradient(b) => {
+                let (p0, p1) = (b.start_point, b.end_point);
+                let pattern = cairo::LinearGradient::new(p0.x, p0.y, p1.x, p1.y);
+
+                Ok(self.set_source_gradient(&b.gradient, &pattern, cairo::Matrix::identity()))
+            }
+            Brush::RadialGradient(b) => {
+                if b.radius_x <= 0.0 || b.radius_y <= 0.0 {
+                    return Ok(false);
+                }
+
+                // cairo only supports circular gradients, so the gradient
+                // is built in a space where the ellipse is a circle of
+                // radius RadiusX and then scaled vertically
+                let k = b.radius_x / b.radius_y;
+                let (o, c) = (b.gradient_origin, b.center);
+                let pattern =
+                    cairo::RadialGradient::new(o.x, o.y * k, 0.0, c.x, c.y * k, b.radius_x);
+
+                let mut matrix = cairo::Matrix::identity();
+                matrix.scale(1.0, 1.0 / k);
+
+                Ok(self.set_source_gradient(&b.gradient, &pattern, matrix))
+            }
+            Brush::Image(b) => {
+                let image = match b.image {
+                    Some(ref image) => image,
+                    None => return Ok(false),
+                };
+
+                let surface = image_surface(image).map_err(RenderError::Cairo)?;
+
+                // The viewbox is in 1/96 inch, while the surface is
+                // in pixels
+                let (width, height) = image.size();
+                let (pixel_width, pixel_height) = image.pixel_size();
+
+                self.set_source_tile(&b.tile, |cr| {
+                    cr.scale(
+                        width / f64::from(pixel_width),
+                        height / f64::from(pixel_height),
+                    );
+                    cr.set_source_surface(&surface, 0.0, 0.0);
+                    cr.paint();
+
+                    Ok(())
+                })
+            }
+            Brush::Visual(b) => self.set_source_tile(&b.tile, |cr| {
+                CairoRenderer::new(cr.clone()).render_children(&b.visual)
+            }),
+        }
+    }
+
+    // Returns the matrix mapping the brush space to user space, given
+    // the matrix that maps the pattern space to the brush space
+    fn brush_matrix(
+        &self,
+        matrix: cairo::Matrix,
+        mapping_mode: MappingMode,
+        transform: Option<RenderTransform>,
+    ) -> cairo::Matrix {
+        let mut matrix = matrix;
+
+        // Relative coordinates are mapped to the bounding box of
+        // the geometry, before applying the brush transform
+        if mapping_mode == MappingMode::RelativeToBoundingBox {
+            let (x1, y1, x2, y2) = self.cr.path_extents();
+            let bbox = cairo::Matrix::new(x2 - x1, 0.0, 0.0, y2 - y1, x1, y1);
+            matrix = cairo::Matrix::multiply(&matrix, &bbox);
+        }
+
+        if let Some(t) = transform {
+            matrix = cairo::Matrix::multiply(&matrix, &t.into());
+        }
+
+        matrix
+    }
+
+    // Sets a pattern repeating the tile as the source. The content is
+    // drawn by `draw` in the coordinate space of the viewbox.
+    fn set_source_tile<F>(&self, tile: &TileBrush, draw: F) -> RenderResult<bool>
+    where
+        F: Fn(&cairo::Context) -> RenderResult<()>,
+    {
+        let (vb, vp) = (tile.viewbox, tile.viewport);
+        if vb.width <= 0.0 || vb.height <= 0.0 || vp.width <= 0.0 || vp.height <= 0.0 {
+            return Ok(false);
+        }
+
+        // The tile is rendered at the resolution of the viewport on
+        // the device, so that the content is not pixelated
+        let viewport = cairo::Matrix::new(vp.width, 0.0, 0.0, vp.height, vp.x, vp.y);
+        let to_user = self.brush_matrix(viewport, tile.viewport_units, tile.transform);
+        let to_device = cairo::Matrix::multiply(&to_user, &self.cr.get_matrix());
+
+        let tile_width = to_device
+            .xx
+            .hypot(to_device.yx)
+            .ceil()
+            .clamp(1.0, MAX_TILE_SIZE);
+        let tile_height = to_device
+            .xy
+            .hypot(to_device.yy)
+            .ceil()
+            .clamp(1.0, MAX_TILE_SIZE);
+
+        let (columns, rows) = match tile.tile_mode {
+            TileMode::None | TileMode::Tile => (1, 1),
+            TileMode::FlipX => (2, 1),
+            TileMode::FlipY => (1, 2),
+            TileMode::FlipXY => (2, 2),
+        };
+
+        let surface = self
+            .cr
+            .get_target()
+            .create_similar(
+                cairo::Content::ColorAlpha,
+                tile_width as i32 * columns,
+                tile_height as i32 * rows,
+            )
+            .map_err(RenderError::Cairo)?;
+
+        let cr = cairo::Context::new(&surface);
+
+        // Odd columns and rows are mirrored images of the tile
+        for row in 0..rows {
+            for column in 0..columns {
+                cr.save();
+                cr.translate(f64::from(column) * tile_width, f64::from(row) * tile_height);
+                if column == 1 {
+                    cr.translate(tile_width, 0.0);
+                    cr.scale(-1.0, 1.0);
+                }
+                if row == 1 {
+                    cr.translate(0.0, tile_height);
+                    cr.scale(1.0, -1.0);
+                }
+                cr.rectangle(0.0, 0.0, tile_width, tile_height);
+                cr.clip();
+                cr.scale(tile_width / vb.width, tile_height / vb.height);
+                cr.translate(-vb.x, -vb.y);
+
+                cr.push_group();
+                let res = draw(&cr);
+                cr.pop_group_to_source();
+                cr.paint_with_alpha(tile.opacity);
+                cr.restore();
+
+                res?;
+            }
+        }
+
+        match cr.status() {
+            cairo::Status::Success => (),
+            status => return Err(RenderError::Cairo(status)),
+        }
+
+        let pattern = cairo::SurfacePattern::create(&surface);
+
+        pattern.set_extend(match tile.tile_mode {
+            TileMode::None => cairo::Extend::None,
+            _ => cairo::Extend::Repeat,
+        });
+
+        // Each tile of the surface is mapped to the viewport
+        let cell = cairo::Matrix::new(
+            vp.width / tile_width,
+            0.0,
+            0.0,
+            vp.height / tile_height,
+            vp.x,
+            vp.y,
+        );
+        let matrix = self.brush_matrix(cell, tile.viewport_units, tile.transform);
+
+        match matrix.try_invert() {
+            Ok(m) => pattern.set_matrix(m),
+            Err(_) => return Ok(false),
+        }
+
+        self.cr.set_source(&pattern);
+
+        Ok(true)
+    }
+
+    // The matrix maps the space of the gradient pattern to the
+    // brush space
+    fn set_source_gradient(
+        &self,
+        gradient: &Gradient,
+        pattern: &cairo::Gradient,
+        matrix: cairo::Matrix,
+    ) -> bool {
+        if gradient.gradient_stops.is_empty() {
+            return false;
+        }
+
+        add_color_stops(pattern, gradient);
+
+        pattern.set_extend(match gradient.spread_method {
+            SpreadMethod::Pad => cairo::Extend::Pad,
+            SpreadMethod::Reflect => cairo::Extend::Reflect,
+            SpreadMethod::Repeat => cairo::Extend::Repeat,
+        });
+
+        let matrix = self.brush_matrix(matrix, gradient.mapping_mode, gradient.transform);
+
+        // The pattern matrix maps user space to pattern space
+        match matrix.try_invert() {
+            Ok(m) => pattern.set_matrix(m),
+            Err(_) => return false,
+        }
+
+        self.cr.set_source(pattern);
+
+        true
+    }
+
+    // Intersects the clip region with the clip geometry, in the
+    // coordinate space of the element
+    fn set_clip(&self, clip: &Option<Clip>) {
+        if let Some(Clip(ref geometry)) = clip {
+            self.set_geometry(geometry, false);
+            self.cr.set_fill_rule(geometry.fill_rule.into());
+            self.cr.clip();
+        }
+    }
+
+    // Renders the content of an element with `draw`, composited with
+    // the opacity and the opacity mask of the element. The mask brush
+    // is mapped to the bounds of the element, or to the clip extents
+    // when the element has no geometry of its own.
+    fn with_opacity<F>(
+        &self,
+        opacity: Option<Opacity>,
+        opacity_mask: &Option<OpacityMask>,
+        bounds: Option<&Geometry>,
+        draw: F,
+    ) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let opacity = opacity.map_or(1.0, |o| o.0);
+
+        if opacity >= 1.0 && opacity_mask.is_none() {
+            return draw();
+        }
+
+        self.cr.push_group();
+        let res = draw();
+        let content = self.cr.pop_group();
+
+        res?;
+
+        let mask = match opacity_mask {
+            Some(OpacityMask(ref brush)) => {
+                match bounds {
+                    Some(geometry) => self.set_geometry(geometry, false),
+                    None => {
+                        let (x1, y1, x2, y2) = self.cr.clip_extents();
+                        self.cr.new_path();
+                        self.cr.rectangle(x1, y1, x2 - x1, y2 - y1);
+                    }
+                }
+
+                // An unsupported mask brush is ignored
+                if self.set_source_brush(brush)? {
+                    Some(self.cr.get_source())
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        self.cr.new_path();
+
+        if let Some(mask) = mask {
+            self.cr.push_group();
+            self.cr.set_source(&content);
+            self.cr.mask(&mask);
+            self.cr.pop_group_to_source();
+        } else {
+            self.cr.set_source(&content);
+        }
+
+        self.cr.paint_with_alpha(opacity);
+
+        Ok(())
+    }
+
+    fn set_stroke_style(&self, path: &Path) {
+        let thickness = path.stroke_thickness.unwrap_or_default().0;
+        self.cr.set_line_width(thickness);
+
+        // cairo does not support different caps for the start and the
+        // end of a line, nor triangle caps
+        self.cr.set_line_cap(match path.line_cap() {
+            LineCap::Flat => cairo::LineCap::Butt,
+            LineCap::Round | LineCap::Triangle => cairo::LineCap::Round,
+            LineCap::Square => cairo::LineCap::Square,
+        });
+
+        let join = path.stroke_line_join.map(|j| j.0).unwrap_or_default();
+        self.cr.set_line_join(match join {
+            LineJoin::Miter => cairo::LineJoin::Miter,
+            LineJoin::Bevel => cairo::LineJoin::Bevel,
+            LineJoin::Round => cairo::LineJoin::Round,
+        });
+
+        let miter_limit = path.stroke_miter_limit.unwrap_or_default();
+        self.cr.set_miter_limit(miter_limit.0);
+
+        // Dashes are expressed in multiples of the stroke thickness
+        if let Some(ref dashes) = path.stroke_dash_array {
+            if !dashes.0.is_empty() && dashes.0.iter().any(|&d| d > 0.0) {
+                let dashes = dashes.0.iter().map(|d| d * thickness).collect::<Vec<_>>();
+                let offset = path.stroke_dash_offset.map(|o| o.0).unwrap_or_default();
+                self.cr.set_dash(&dashes, offset * thickness);
+            }
+        }
     }
 }
 
 impl Renderer for CairoRenderer {
-    fn render_canvas(&self, _canvas: &Canvas) -> RenderResult<()> {
-        Ok(())
+    fn render_canvas(&self, canvas: &Canvas) -> RenderResult<()> {
+        self.with_saved_state(|| {
+            if let Some(t) = canvas.render_transform {
+                self.cr.transform(t.into())
+            }
+
+            self.set_clip(&canvas.clip);
+
+            self.with_tags(&canvas.name, &canvas.navigate_uri, || {
+                self.with_opacity(canvas.opacity, &canvas.opacity_mask, None, || {
+                    self.render_children(&canvas.children)
+                })
+            })
+        })
     }
 
-    fn render_glyphs(&self, _glyphs: &Glyphs) -> RenderResult<()> {
-        Ok(())
+    fn render_glyphs(&self, glyphs: &Glyphs) -> RenderResult<()> {
+        let fill = match glyphs.fill {
+            Some(ref fill) => fill,
+            None => return Ok(()),
+        };
+
+        let outline = match font::outline(glyphs) {
+            Some(outline) => outline,
+            None => return Ok(()),
+        };
+
+        self.with_saved_state(|| {
+            if let Some(t) = glyphs.render_transform {
+                self.cr.transform(t.into())
+            }
+
+            self.set_clip(&glyphs.clip);
+
+            self.with_tags(&glyphs.name, &glyphs.navigate_uri, || {
+                let opacity_mask = &glyphs.opacity_mask;
+                self.with_opacity(glyphs.opacity, opacity_mask, Some(&outline), || {
+                    self.set_geometry(&outline, false);
+                    self.cr.set_fill_rule(outline.fill_rule.into());
+
+                    if !self.set_source_brush(&fill.0)? {
+                        return Ok(());
+                    }
+
+                    #[cfg(feature = "pdf")]
+                    {
+                        if self.show_glyphs(glyphs) {
+                            self.cr.new_path();
+                            return Ok(());
+                        }
+                    }
+
+                    // The bold simulation is emulated by stroking the outline
+                    if let Some(width) = font::bold_stroke_width(glyphs) {
+                        self.cr.fill_preserve();
+                        self.cr.set_line_width(width);
+                        self.cr.set_line_join(cairo::LineJoin::Round);
+                        self.cr.stroke();
+                    } else {
+                        self.cr.fill();
+                    }
+
+                    Ok(())
+                })
+            })
+        })
     }
 
     fn render_path(&self, path: &Path) -> RenderResult<()> {
-        if let Some(t) = path.render_transform {
-            self.cr.transform(t.into())
+        let data = match path.data {
+            Some(ref data) => data,
+            None => return Ok(()),
+        };
+
+        self.with_saved_state(|| {
+            if let Some(t) = path.render_transform {
+                self.cr.transform(t.into())
+            }
+
+            self.set_clip(&path.clip);
+
+            self.with_tags(&path.name, &path.navigate_uri, || {
+                self.with_opacity(path.opacity, &path.opacity_mask, Some(data), || {
+                    if let Some(ref fill) = path.fill {
+                        self.set_geometry(data, false);
+                        self.cr.set_fill_rule(data.fill_rule.into());
+
+                        if self.set_source_brush(&fill.0)? {
+                            self.cr.fill();
+                        }
+                    }
+
+                    if let Some(ref stroke) = path.stroke {
+                        self.set_geometry(data, true);
+                        self.set_stroke_style(path);
+
+                        if self.set_source_brush(&stroke.0)? {
+                            self.cr.stroke();
+                        }
+                    }
+
+                    Ok(())
+                })
+            })
+        })
+    }
+}
+
+// Maximum size in pixels of the surface used to render a tile
+const MAX_TILE_SIZE: f64 = 4096.0;
+
+// Converts the image to a cairo surface, with premultiplied alpha
+fn image_surface(image: &Image) -> Result<cairo::ImageSurface, cairo::Status> {
+    let (width, height) = image.pixel_size();
+    let mut surface =
+        cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
+
+    let stride = surface.get_stride() as usize;
+    let mut data = surface
+        .get_data()
+        .map_err(|_| cairo::Status::SurfaceTypeMismatch)?;
+
+    let rows = image.pixels().chunks_exact(width as usize * 4);
+    for (y, row) in rows.enumerate() {
+        for (x, p) in row.chunks_exact(4).enumerate() {
+            let a = u32::from(p[3]);
+            let premultiply = |c: u8| (u32::from(c) * a + 127) / 255;
+            let pixel =
+                a << 24 | premultiply(p[0]) << 16 | premultiply(p[1]) << 8 | premultiply(p[2]);
+
+            let offset = y * stride + x * 4;
+            data[offset..offset + 4].copy_from_slice(&pixel.to_ne_bytes());
         }
+    }
 
-        Ok(())
+    drop(data);
+
+    Ok(surface)
+}
+
+fn add_color_stops(pattern: &cairo::Gradient, gradient: &Gradient) {
+    for (offset, color) in gradient.color_stops() {
+        let (r, g, b, a) = color.to_rgba();
+        pattern.add_color_stop_rgba(offset, r, g, b, a * gradient.opacity);
+    }
+}
+
+struct CairoPathBuilder<'a>(&'a cairo::Context);
+
+impl<'a> PathBuilder for CairoPathBuilder<'a> {
+    fn move_to(&mut self, p: Point) {
+        self.0.move_to(p.x, p.y);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.0.line_to(p.x, p.y);
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.0.curve_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
     }
 }
 
@@ -39,3 +756,28 @@ impl From<RenderTransform> for cairo::Matrix {
         Self::new(t.xx, t.yx, t.xy, t.yy, t.x0, t.y0)
     }
 }
+
+impl From<FillRule> for cairo::FillRule {
+    #[inline]
+    fn from(r: FillRule) -> Self {
+        match r {
+            FillRule::EvenOdd => Self::EvenOdd,
+            FillRule::NonZero => Self::Winding,
+        }
+    }
+}
+
+// Returns the name of the PDF destination for a URI inside the
+// package: the link target for URIs with a fragment, otherwise the page
+pub(crate) fn destination_name(uri: &str) -> String {
+    // FIXME: link target names are only unique inside a document
+    match uri.split_once('#') {
+        Some((_, fragment)) => String::from(fragment),
+        None => String::from(uri.trim_start_matches('/')),
+    }
+}
+
+// Quotes a string for the attributes of cairo tags
+pub(crate) fn quote_attribute(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
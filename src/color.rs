@@ -0,0 +1,387 @@
+use std::str::FromStr;
+
+/// A color, in one of the syntaxes described in section 11.1 of the
+/// XPS spec
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Color {
+    /// sRGB color with 8 bits per channel, `#RRGGBB` or `#AARRGGBB`
+    SRgb { a: u8, r: u8, g: u8, b: u8 },
+    /// scRGB color, `sc#R,G,B` or `sc#A,R,G,B`. The components are
+    /// linear and may be outside of the [0, 1] range.
+    ScRgb { a: f64, r: f64, g: f64, b: f64 },
+    /// Color in the space of an ICC profile part of the package,
+    /// `ContextColor <profile> A,C1,C2,...`. The color space is set
+    /// when the profile is loaded.
+    Context {
+        profile: String, // FIXME: use a type for URI
+        space: Option<ColorSpace>,
+        a: f64,
+        components: Vec<f64>,
+    },
+}
+
+/// The color space of an ICC profile, among the ones whose colors
+/// can be converted to sRGB
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+}
+
+impl ColorSpace {
+    /// Returns the color space of the ICC profile data, or the
+    /// signature of the color space when it is not supported
+    pub fn from_profile(data: &[u8]) -> Result<Self, String> {
+        // The signature follows the size, CMM, version, class fields
+        // of the header
+        let signature = data.get(16..20).ok_or("invalid profile")?;
+
+        match signature {
+            b"GRAY" => Ok(ColorSpace::Gray),
+            b"RGB " => Ok(ColorSpace::Rgb),
+            b"CMYK" => Ok(ColorSpace::Cmyk),
+            _ => Err(String::from_utf8_lossy(signature).trim_end().to_string()),
+        }
+    }
+}
+
+impl Color {
+    /// Returns the alpha of the color in the [0, 1] range
+    pub fn alpha(&self) -> f64 {
+        match *self {
+            Color::SRgb { a, .. } => f64::from(a) / 255.0,
+            Color::ScRgb { a, .. } => a.clamp(0.0, 1.0),
+            Color::Context { a, .. } => a.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Returns the (r, g, b, a) components of the color in sRGB,
+    /// in the [0, 1] range
+    pub fn to_rgba(&self) -> (f64, f64, f64, f64) {
+        match *self {
+            Color::SRgb { a, r, g, b } => (
+                f64::from(r) / 255.0,
+                f64::from(g) / 255.0,
+                f64::from(b) / 255.0,
+                f64::from(a) / 255.0,
+            ),
+            Color::ScRgb { r, g, b, .. } => (
+                linear_to_srgb(r),
+                linear_to_srgb(g),
+                linear_to_srgb(b),
+                self.alpha(),
+            ),
+            Color::Context {
+                space,
+                ref components,
+                ..
+            } => {
+                let (r, g, b) = context_to_srgb(space, components);
+                (r, g, b, self.alpha())
+            }
+        }
+    }
+
+    /// Returns the (r, g, b, a) components of the color in sRGB with
+    /// 8 bits per channel, for renderers that cannot do wide gamut
+    pub fn to_srgb8(&self) -> (u8, u8, u8, u8) {
+        let (r, g, b, a) = self.to_rgba();
+        let c = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        (c(r), c(g), c(b), c(a))
+    }
+
+    /// Returns the (r, g, b, a) components of the color in linear
+    /// scRGB, clamped to the [0, 1] range
+    pub fn to_sc_rgba(&self) -> (f64, f64, f64, f64) {
+        match *self {
+            Color::ScRgb { a, r, g, b } => (
+                r.clamp(0.0, 1.0),
+                g.clamp(0.0, 1.0),
+                b.clamp(0.0, 1.0),
+                a.clamp(0.0, 1.0),
+            ),
+            _ => {
+                let (r, g, b, a) = self.to_rgba();
+                (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+            }
+        }
+    }
+
+    /// Returns the color at `t` between this color and `other`,
+    /// interpolating linearly in the scRGB space
+    pub fn interpolate_sc_rgb(&self, other: &Color, t: f64) -> Color {
+        let (r0, g0, b0, a0) = self.to_sc_rgba();
+        let (r1, g1, b1, a1) = other.to_sc_rgba();
+
+        let lerp = |c0: f64, c1: f64| c0 + (c1 - c0) * t;
+
+        Color::ScRgb {
+            a: lerp(a0, a1),
+            r: lerp(r0, r1),
+            g: lerp(g0, g1),
+            b: lerp(b0, b1),
+        }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::SRgb {
+            a: 255,
+            r: 0,
+            g: 0,
+            b: 0,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(hex) = s.strip_prefix('#') {
+            parse_srgb(hex)
+        } else if let Some(values) = s.strip_prefix("sc#") {
+            parse_sc_rgb(values)
+        } else if let Some(context) = s.strip_prefix("ContextColor ") {
+            parse_context_color(context)
+        } else {
+            Err(())
+        }
+    }
+}
+
+fn parse_srgb(hex: &str) -> Result<Color, ()> {
+    // from_str_radix also accepts a sign
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(());
+    }
+
+    let component = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or(())
+    };
+
+    match hex.len() {
+        6 => Ok(Color::SRgb {
+            a: 255,
+            r: component(0)?,
+            g: component(2)?,
+            b: component(4)?,
+        }),
+        8 => Ok(Color::SRgb {
+            a: component(0)?,
+            r: component(2)?,
+            g: component(4)?,
+            b: component(6)?,
+        }),
+        _ => Err(()),
+    }
+}
+
+fn parse_sc_rgb(s: &str) -> Result<Color, ()> {
+    let values = parse_floats(s)?;
+
+    match values[..] {
+        [r, g, b] => Ok(Color::ScRgb { a: 1.0, r, g, b }),
+        [a, r, g, b] => Ok(Color::ScRgb { a, r, g, b }),
+        _ => Err(()),
+    }
+}
+
+fn parse_context_color(s: &str) -> Result<Color, ()> {
+    let s = s.trim_start();
+    let (profile, values) = s.split_at(s.find(char::is_whitespace).ok_or(())?);
+
+    let mut values = parse_floats(values)?;
+
+    // The alpha is followed by at least one color channel and
+    // at most eight, as in an 8 channel profile
+    if values.len() < 2 || values.len() > 9 {
+        return Err(());
+    }
+
+    let a = values.remove(0);
+
+    Ok(Color::Context {
+        profile: profile.to_string(),
+        space: None,
+        a,
+        components: values,
+    })
+}
+
+fn parse_floats(s: &str) -> Result<Vec<f64>, ()> {
+    s.split(',')
+        .map(|v| v.trim().parse::<f64>().map_err(|_| ()))
+        .collect()
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = c.clamp(0.0, 1.0);
+
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// FIXME: colors are not color managed, the channels are interpreted
+// according to the color space of the profile. When it is not known,
+// they are interpreted according to their number: gray, RGB or CMYK.
+fn context_to_srgb(space: Option<ColorSpace>, components: &[f64]) -> (f64, f64, f64) {
+    let c = |i: usize| {
+        components
+            .get(i)
+            .copied()
+            .unwrap_or_default()
+            .clamp(0.0, 1.0)
+    };
+
+    let space = space.unwrap_or(match components.len() {
+        1 => ColorSpace::Gray,
+        4 => ColorSpace::Cmyk,
+        _ => ColorSpace::Rgb,
+    });
+
+    match space {
+        ColorSpace::Gray => (c(0), c(0), c(0)),
+        ColorSpace::Rgb => (c(0), c(1), c(2)),
+        ColorSpace::Cmyk => (
+            (1.0 - c(0)) * (1.0 - c(3)),
+            (1.0 - c(1)) * (1.0 - c(3)),
+            (1.0 - c(2)) * (1.0 - c(3)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Color {
+        s.parse::<Color>().unwrap()
+    }
+
+    #[test]
+    fn srgb() {
+        assert_eq!(
+            parse("#FF8000"),
+            Color::SRgb {
+                a: 255,
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert_eq!(
+            parse(" #80ff8000 "),
+            Color::SRgb {
+                a: 128,
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert_eq!(parse("#80FF8000").to_srgb8(), (255, 128, 0, 128));
+
+        for s in &["#FF80", "#FF80001", "#GG8000", "FF8000", "#+F8000"] {
+            assert!(s.parse::<Color>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn sc_rgb() {
+        assert_eq!(
+            parse("sc#1,0.5,0"),
+            Color::ScRgb {
+                a: 1.0,
+                r: 1.0,
+                g: 0.5,
+                b: 0.0
+            }
+        );
+        assert_eq!(
+            parse("sc#0.5, 1.5, -0.5, 1e-1"),
+            Color::ScRgb {
+                a: 0.5,
+                r: 1.5,
+                g: -0.5,
+                b: 0.1
+            }
+        );
+
+        // The components are linear and clamped when converted to sRGB
+        assert_eq!(parse("sc#0.5,1.5,-0.5,0.2").to_srgb8(), (255, 0, 124, 128));
+
+        for s in &["sc#1,0", "sc#1,0,0,0,0", "sc#a,0,0", "sc#"] {
+            assert!(s.parse::<Color>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn context_color() {
+        assert_eq!(
+            parse("ContextColor /Resources/cmyk.icc 0.5,0,1,1,0"),
+            Color::Context {
+                profile: String::from("/Resources/cmyk.icc"),
+                space: None,
+                a: 0.5,
+                components: vec![0.0, 1.0, 1.0, 0.0],
+            }
+        );
+
+        // Without the profile the space is guessed from the channels
+        assert_eq!(
+            parse("ContextColor gray.icc 1,0.5").to_srgb8(),
+            (128, 128, 128, 255)
+        );
+        assert_eq!(
+            parse("ContextColor cmyk.icc 1,0,1,1,0").to_srgb8(),
+            (255, 0, 0, 255)
+        );
+
+        let mut color = parse("ContextColor rgb.icc 1,0,1,1,0");
+        if let Color::Context { ref mut space, .. } = color {
+            *space = Some(ColorSpace::Rgb);
+        }
+        assert_eq!(color.to_srgb8(), (0, 255, 255, 255));
+
+        for s in &[
+            "ContextColor cmyk.icc",
+            "ContextColor cmyk.icc 1",
+            "ContextColor 1,0",
+        ] {
+            assert!(s.parse::<Color>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn profile_color_space() {
+        let mut profile = vec![0; 128];
+        profile[16..20].copy_from_slice(b"CMYK");
+        assert_eq!(ColorSpace::from_profile(&profile), Ok(ColorSpace::Cmyk));
+
+        profile[16..20].copy_from_slice(b"RGB ");
+        assert_eq!(ColorSpace::from_profile(&profile), Ok(ColorSpace::Rgb));
+
+        profile[16..20].copy_from_slice(b"Lab ");
+        assert_eq!(ColorSpace::from_profile(&profile), Err(String::from("Lab")));
+
+        assert!(ColorSpace::from_profile(&profile[..10]).is_err());
+    }
+}
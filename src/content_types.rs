@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::Result;
+
+#[rustfmt::skip]
+mod ns {
+    pub const CONTENT_TYPES: &str = "http://schemas.openxmlformats.org/package/2006/content-types";
+}
+
+#[rustfmt::skip]
+pub mod ty {
+    pub const OBFUSCATED_FONT: &str = "application/vnd.ms-package.obfuscated-opentype";
+}
+
+/// The content types of the parts, from the `[Content_Types].xml`
+/// item of the package. Extensions and part names are case insensitive.
+#[derive(Debug, Default)]
+pub struct ContentTypes {
+    defaults: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+}
+
+impl ContentTypes {
+    pub fn parse(content_types: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(content_types)?;
+
+        let mut res = Self::default();
+
+        for node in doc
+            .root()
+            .children()
+            .filter(|n| n.has_tag_name((ns::CONTENT_TYPES, "Types")))
+        {
+            for node in node.children() {
+                let ty = match node.attribute("ContentType") {
+                    Some(ty) => String::from(ty),
+                    None => continue,
+                };
+
+                if node.has_tag_name((ns::CONTENT_TYPES, "Default")) {
+                    if let Some(extension) = node.attribute("Extension") {
+                        res.defaults.insert(extension.to_lowercase(), ty);
+                    }
+                } else if node.has_tag_name((ns::CONTENT_TYPES, "Override")) {
+                    if let Some(part_name) = node.attribute("PartName") {
+                        res.overrides.insert(part_name.to_lowercase(), ty);
+                    }
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Returns the content type of the part with the given name in
+    /// the archive
+    pub fn get<P: AsRef<Path>>(&self, name: P) -> Option<&str> {
+        let name = name.as_ref();
+
+        // Part names are absolute, archive item names are not
+        let part_name = format!("/{}", name.display()).to_lowercase();
+
+        self.overrides
+            .get(&part_name)
+            .or_else(|| {
+                let extension = name.extension()?.to_str()?.to_lowercase();
+                self.defaults.get(&extension)
+            })
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_types() {
+        let content_types = ContentTypes::parse(
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+                 <Default Extension="ODTTF" ContentType="application/vnd.ms-package.obfuscated-opentype"/>
+                 <Default Extension="ttf" ContentType="application/vnd.ms-opentype"/>
+                 <Override PartName="/Resources/Font.TTF" ContentType="application/vnd.ms-package.obfuscated-opentype"/>
+               </Types>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            content_types.get("Resources/a.odttf"),
+            Some(ty::OBFUSCATED_FONT)
+        );
+        assert_eq!(
+            content_types.get("Resources/b.ttf"),
+            Some("application/vnd.ms-opentype")
+        );
+        assert_eq!(
+            content_types.get("resources/font.ttf"),
+            Some(ty::OBFUSCATED_FONT)
+        );
+        assert_eq!(content_types.get("Resources/c.png"), None);
+    }
+}
@@ -3,12 +3,48 @@ pub type ParseResult<T> = std::result::Result<T, ParseError>;
 #[derive(Debug)]
 pub enum ParseError {
     MissingBrush,
+    InvalidBrush,
+    InvalidPathData,
+    InvalidFontGuid(String),
+    MissingResourceKey,
+    DuplicateResourceKey(String),
+    UndefinedResource(String),
+    ResourceUsedBeforeDeclaration(String),
+    InvalidResource(String),
+    InvalidRemoteResourceDictionary(String, String),
+    DuplicateProperty(String),
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ParseError::MissingBrush => write!(f, "Missing brush element"),
+            ParseError::InvalidBrush => write!(f, "Invalid brush"),
+            ParseError::InvalidPathData => write!(f, "Invalid path data"),
+            ParseError::InvalidFontGuid(name) => {
+                write!(f, "Invalid GUID in obfuscated font name {}", name)
+            }
+            ParseError::MissingResourceKey => write!(f, "Missing x:Key in resource dictionary"),
+            ParseError::DuplicateResourceKey(key) => {
+                write!(f, "Duplicate x:Key {} in resource dictionary", key)
+            }
+            ParseError::UndefinedResource(key) => write!(f, "Undefined resource {}", key),
+            ParseError::ResourceUsedBeforeDeclaration(key) => {
+                write!(f, "Resource {} used before its declaration", key)
+            }
+            ParseError::InvalidResource(key) => {
+                write!(f, "Resource {} has the wrong type for the property", key)
+            }
+            ParseError::InvalidRemoteResourceDictionary(uri, cause) => {
+                write!(f, "Invalid remote resource dictionary {}: {}", uri, cause)
+            }
+            ParseError::DuplicateProperty(name) => {
+                write!(
+                    f,
+                    "Property {} given both as attribute and as element",
+                    name
+                )
+            }
         }
     }
 }
@@ -20,7 +56,7 @@ pub type RenderResult<T> = std::result::Result<T, RenderError>;
 #[derive(Debug)]
 pub enum RenderError {
     #[cfg(feature = "cairo-renderer")]
-    Cairo, // FIXME: wrap the cairo error
+    Cairo(cairo::Status),
 
     Unknown,
 }
@@ -29,7 +65,7 @@ impl std::fmt::Display for RenderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             #[cfg(feature = "cairo-renderer")]
-            RenderError::Cairo => write!(f, "Cairo error"),
+            RenderError::Cairo(status) => write!(f, "Cairo error: {}", status),
             RenderError::Unknown => write!(f, "Unknown rendering error"),
         }
     }
@@ -46,6 +82,7 @@ pub enum Error {
     Utf16(std::string::FromUtf16Error),
     Zip(zip::result::ZipError),
     Xml(roxmltree::Error),
+    Image(image::ImageError),
     Xps(ParseError),
     Render(RenderError),
 }
@@ -58,6 +95,7 @@ impl std::fmt::Display for Error {
             Error::Utf8(e) => e.fmt(f),
             Error::Utf16(e) => e.fmt(f),
             Error::Xml(e) => e.fmt(f),
+            Error::Image(e) => e.fmt(f),
             Error::Xps(e) => e.fmt(f),
             Error::Render(e) => e.fmt(f),
         }
@@ -96,6 +134,12 @@ impl From<roxmltree::Error> for Error {
     }
 }
 
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::Image(err)
+    }
+}
+
 impl From<ParseError> for Error {
     fn from(err: ParseError) -> Self {
         Error::Xps(err)
@@ -0,0 +1,430 @@
+use crate::geometry::{Figure, FillRule, Geometry, Point, Segment, SegmentKind};
+use crate::renderer::{ClusterGlyph, Glyphs, StyleSimulations};
+
+// Amount of emboldening and slant applied by the style simulations,
+// see section 5.1.8 of the XPS spec
+const BOLD_ADVANCE_RATIO: f64 = 0.02;
+const ITALIC_SKEW_DEGREES: f64 = 20.0;
+
+// The glyph drawn for the characters missing from the font
+const NOTDEF_GLYPH: u16 = 0;
+
+/// A TrueType or OpenType font loaded from the package
+pub(crate) struct Font {
+    data: Vec<u8>,
+    index: u32,
+    // The metrics are read once when loading the font, in font units
+    units_per_em: f64,
+    ascent: f64,
+    descent: f64,
+    advances: Vec<u16>,
+}
+
+impl Font {
+    /// Returns a font for the face `index` of the given font data,
+    /// or `None` if the data is not a valid font.
+    pub fn new(data: Vec<u8>, index: u32) -> Option<Self> {
+        let face = ttf_parser::Font::from_data(&data, index)?;
+
+        let units_per_em = face.units_per_em().filter(|&u| u > 0)?;
+        let ascent = f64::from(face.ascender());
+        let descent = -f64::from(face.descender());
+        let advances = (0..face.number_of_glyphs())
+            .map(|g| {
+                face.glyph_hor_advance(ttf_parser::GlyphId(g))
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        Some(Self {
+            data,
+            index,
+            units_per_em: f64::from(units_per_em),
+            ascent,
+            descent,
+            advances,
+        })
+    }
+
+    // Parses the font tables, which are only needed for the cmap and
+    // the glyph outlines
+    fn face(&self) -> Option<ttf_parser::Font<'_>> {
+        ttf_parser::Font::from_data(&self.data, self.index)
+    }
+
+    /// Returns the data of the font file
+    #[cfg(feature = "pdf")]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the index of the face in the font file
+    #[cfg(feature = "pdf")]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn units_per_em(&self) -> f64 {
+        self.units_per_em
+    }
+
+    /// Returns the distance from the baseline to the top of the
+    /// glyphs, in font units
+    pub fn ascent(&self) -> f64 {
+        self.ascent
+    }
+
+    /// Returns the distance from the baseline to the bottom of the
+    /// glyphs, in font units
+    pub fn descent(&self) -> f64 {
+        self.descent
+    }
+
+    /// Returns the horizontal advance of the glyph, in font units
+    pub fn advance(&self, glyph: u16) -> f64 {
+        self.advances
+            .get(usize::from(glyph))
+            .copied()
+            .map(f64::from)
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font")
+            .field("size", &self.data.len())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A glyph of a `Glyphs` element, positioned in the element
+/// coordinate space
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct PlacedGlyph {
+    pub id: u16,
+    pub origin: Point,
+    pub advance: f64,
+}
+
+/// Computes the position of each glyph of the run, using the advances
+/// and offsets of the `Indices` attribute when available and the font
+/// metrics otherwise.
+pub(crate) fn layout(glyphs: &Glyphs, font: &Font) -> Vec<PlacedGlyph> {
+    let em = glyphs.font_rendering_em_size;
+    let scale = em / font.units_per_em();
+
+    let bold = glyphs
+        .style_simulations
+        .is_some_and(StyleSimulations::is_bold);
+
+    let rtl = glyphs.is_rtl();
+
+    let face = font.face();
+
+    let mut res = Vec::new();
+
+    let (mut x, y) = glyphs.origin;
+
+    for glyph in glyphs.clusters() {
+        let entry = glyph.entry;
+
+        // Glyphs that are not drawn still move the following glyphs
+        let id = match glyph_id(&glyph, face.as_ref()) {
+            Some(id) => id,
+            None => {
+                let advance = entry.advance.unwrap_or_default() * em / 100.0;
+                if rtl {
+                    x -= advance;
+                } else {
+                    x += advance;
+                }
+                continue;
+            }
+        };
+
+        // Advances and offsets are in hundredths of the em size
+        let u = entry.u_offset * em / 100.0;
+        let v = entry.v_offset * em / 100.0;
+
+        let advance = match entry.advance {
+            Some(advance) => advance * em / 100.0,
+            None if bold => font.advance(id) * scale + em * BOLD_ADVANCE_RATIO,
+            None => font.advance(id) * scale,
+        };
+
+        // Right to left runs start at the origin and advance to the
+        // left, with offsets mirrored accordingly
+        let origin = if rtl {
+            x -= advance;
+            Point::new(x - u, y - v)
+        } else {
+            let origin = Point::new(x + u, y - v);
+            x += advance;
+            origin
+        };
+
+        res.push(PlacedGlyph {
+            id,
+            origin,
+            advance,
+        });
+    }
+
+    res
+}
+
+/// Returns the clusters of the glyphs placed by `layout`, as the
+/// number of bytes of the UnicodeString and the number of glyphs
+/// of each cluster, in logical order
+#[cfg(feature = "pdf")]
+pub(crate) fn text_clusters(glyphs: &Glyphs, font: &Font) -> Vec<(usize, usize)> {
+    let face = font.face();
+
+    let mut res: Vec<(usize, usize)> = Vec::new();
+
+    for glyph in glyphs.clusters() {
+        if glyph.cluster_start {
+            res.push((glyph.text.len(), 0));
+        }
+
+        if glyph_id(&glyph, face.as_ref()).is_some() {
+            if let Some(cluster) = res.last_mut() {
+                cluster.1 += 1;
+            }
+        }
+    }
+
+    // Glyphs past the end of the text that are not drawn
+    res.retain(|&c| c != (0, 0));
+
+    res
+}
+
+// Without an explicit index, the glyph is looked up in the font
+// cmap, which is only possible for one to one clusters. Characters
+// that cannot be looked up are drawn with the .notdef glyph, glyphs
+// past the end of the text without an index are not drawn.
+fn glyph_id(glyph: &ClusterGlyph, face: Option<&ttf_parser::Font<'_>>) -> Option<u16> {
+    if let Some(index) = glyph.entry.index {
+        return Some(index);
+    }
+
+    if glyph.text.is_empty() {
+        return None;
+    }
+
+    let id = glyph
+        .character
+        .zip(face)
+        .and_then(|(c, face)| face.glyph_index(c))
+        .map_or(NOTDEF_GLYPH, |g| g.0);
+
+    Some(id)
+}
+
+/// Returns the horizontal skew of the glyphs applied by the italic
+/// simulation, as the tangent of the angle
+pub(crate) fn italic_skew(glyphs: &Glyphs) -> f64 {
+    let italic = glyphs
+        .style_simulations
+        .is_some_and(StyleSimulations::is_italic);
+
+    if italic {
+        ITALIC_SKEW_DEGREES.to_radians().tan()
+    } else {
+        0.0
+    }
+}
+
+/// Returns the outline of the glyph run as a geometry in the coordinate
+/// space of the `Glyphs` element, with the italic simulation applied.
+pub(crate) fn outline(glyphs: &Glyphs) -> Option<Geometry> {
+    let font = glyphs.font.as_ref()?;
+    let face = font.face()?;
+
+    let mut builder = OutlineBuilder {
+        figures: Vec::new(),
+        figure: None,
+        origin: Point::default(),
+        scale: glyphs.font_rendering_em_size / font.units_per_em(),
+        skew: italic_skew(glyphs),
+    };
+
+    for glyph in layout(glyphs, font) {
+        builder.origin = glyph.origin;
+        face.outline_glyph(ttf_parser::GlyphId(glyph.id), &mut builder);
+        builder.finish_figure();
+    }
+
+    Some(Geometry {
+        fill_rule: FillRule::NonZero,
+        figures: builder.figures,
+        transform: None,
+    })
+}
+
+/// Returns the width of the stroke used to embolden the glyph outlines,
+/// if the bold simulation is enabled.
+pub(crate) fn bold_stroke_width(glyphs: &Glyphs) -> Option<f64> {
+    glyphs
+        .style_simulations
+        .filter(|s| s.is_bold())
+        .map(|_| glyphs.font_rendering_em_size * BOLD_ADVANCE_RATIO)
+}
+
+// Converts glyph outlines from font units (y up) to figures
+// in the coordinate space of the Glyphs element (y down)
+struct OutlineBuilder {
+    figures: Vec<Figure>,
+    figure: Option<Figure>,
+    origin: Point,
+    scale: f64,
+    skew: f64,
+}
+
+impl OutlineBuilder {
+    fn point(&self, x: f32, y: f32) -> Point {
+        let x = f64::from(x) * self.scale;
+        let y = f64::from(y) * self.scale;
+
+        Point::new(self.origin.x + x + self.skew * y, self.origin.y - y)
+    }
+
+    fn push_segment(&mut self, kind: SegmentKind) {
+        if let Some(ref mut figure) = self.figure {
+            figure.segments.push(Segment::new(kind));
+        }
+    }
+
+    fn finish_figure(&mut self) {
+        if let Some(figure) = self.figure.take() {
+            self.figures.push(figure);
+        }
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish_figure();
+        self.figure = Some(Figure::new(self.point(x, y)));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.push_segment(SegmentKind::Line(p));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let c = self.point(x1, y1);
+        let p = self.point(x, y);
+        self.push_segment(SegmentKind::QuadraticBezier(c, p));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let c1 = self.point(x1, y1);
+        let c2 = self.point(x2, y2);
+        let p = self.point(x, y);
+        self.push_segment(SegmentKind::CubicBezier(c1, c2, p));
+    }
+
+    fn close(&mut self) {
+        if let Some(ref mut figure) = self.figure {
+            figure.is_closed = true;
+        }
+        self.finish_figure();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{Indices, UnicodeString};
+
+    // A font with 1000 units per em and three glyphs: .notdef, 500
+    // units wide, then 'a' and 'b', 600 and 700 units wide
+    fn font() -> Font {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+
+        let mut hhea = vec![0; 36];
+        hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+
+        let maxp = [0, 0, 0x50, 0, 0, 3].to_vec();
+
+        let hmtx = [500u16, 0, 600, 0, 700, 0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        // A format 6 subtable mapping 'a' and 'b' to glyphs 1 and 2
+        let cmap = [0u16, 1, 0, 3, 0, 12, 6, 14, 0, 0x61, 2, 1, 2]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        let tables = [
+            (b"cmap", cmap),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"maxp", maxp),
+        ];
+
+        let mut data = vec![0, 1, 0, 0, 0, tables.len() as u8, 0, 0, 0, 0, 0, 0];
+        let mut offset = data.len() + 16 * tables.len();
+
+        for (tag, table) in tables.iter() {
+            data.extend_from_slice(&tag[..]);
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+
+        for (_, table) in tables.iter() {
+            data.extend_from_slice(table);
+        }
+
+        Font::new(data, 0).unwrap()
+    }
+
+    fn glyphs(text: &str, indices: Option<&str>) -> Glyphs {
+        Glyphs {
+            font_rendering_em_size: 10.0,
+            unicode_string: Some(UnicodeString(String::from(text))),
+            indices: indices.map(|i| i.parse::<Indices>().unwrap()),
+            ..Glyphs::default()
+        }
+    }
+
+    fn placed(glyphs: &Glyphs) -> Vec<(u16, f64)> {
+        layout(glyphs, &font())
+            .iter()
+            .map(|g| (g.id, g.origin.x))
+            .collect()
+    }
+
+    #[test]
+    fn unmapped_character() {
+        // The character missing from the font is drawn with .notdef
+        // and its advance
+        assert_eq!(
+            placed(&glyphs("axb", None)),
+            [(1, 0.0), (0, 6.0), (2, 11.0)]
+        );
+
+        assert_eq!(
+            placed(&glyphs("axb", Some(",30;,40;,20"))),
+            [(1, 0.0), (0, 3.0), (2, 7.0)]
+        );
+    }
+
+    #[test]
+    fn glyphs_past_the_text() {
+        // A glyph without index past the end of the text is not
+        // drawn, but its advance moves the following glyphs
+        assert_eq!(placed(&glyphs("a", Some(";,50;2"))), [(1, 0.0), (2, 11.0)]);
+    }
+}
@@ -0,0 +1,817 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use crate::error::ParseError;
+use crate::renderer::RenderTransform;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum FillRule {
+    #[default]
+    EvenOdd,
+    NonZero,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum SweepDirection {
+    Clockwise,
+    Counterclockwise,
+}
+
+/// An elliptical arc from the current point to `point`
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Arc {
+    pub point: Point,
+    pub size: (f64, f64),
+    pub rotation_angle: f64,
+    pub is_large_arc: bool,
+    pub sweep_direction: SweepDirection,
+}
+
+impl Arc {
+    /// Approximates the arc starting at `from` with cubic bezier
+    /// curves, each one given as (control1, control2, end).
+    pub fn to_cubics(self, from: Point) -> Vec<(Point, Point, Point)> {
+        let to = self.point;
+
+        if from == to {
+            return Vec::new();
+        }
+
+        let (mut rx, mut ry) = (self.size.0.abs(), self.size.1.abs());
+
+        // Degenerate arcs are drawn as straight lines
+        if rx == 0.0 || ry == 0.0 {
+            return vec![(from, to, to)];
+        }
+
+        // Endpoint to center parameterization, see the SVG spec, F.6.5
+        let (sin_phi, cos_phi) = self.rotation_angle.to_radians().sin_cos();
+
+        let dx2 = (from.x - to.x) / 2.0;
+        let dy2 = (from.y - to.y) / 2.0;
+        let x1 = cos_phi * dx2 + sin_phi * dy2;
+        let y1 = -sin_phi * dx2 + cos_phi * dy2;
+
+        // Scale up the radii if they are too small to reach the end point
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let clockwise = self.sweep_direction == SweepDirection::Clockwise;
+
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coef = (num / den).max(0.0).sqrt();
+        if self.is_large_arc == clockwise {
+            coef = -coef;
+        }
+
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+
+        let cx = cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) / 2.0;
+        let cy = sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) / 2.0;
+
+        let ux = (x1 - cx1) / rx;
+        let uy = (y1 - cy1) / ry;
+        let vx = (-x1 - cx1) / rx;
+        let vy = (-y1 - cy1) / ry;
+
+        let theta1 = uy.atan2(ux);
+        let mut delta = (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+
+        if clockwise && delta < 0.0 {
+            delta += 2.0 * PI;
+        } else if !clockwise && delta > 0.0 {
+            delta -= 2.0 * PI;
+        }
+
+        // Split in segments of at most 90 degrees
+        let n = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = delta / n as f64;
+        let t = 4.0 / 3.0 * (step / 4.0).tan();
+
+        let point_at = |a: f64| {
+            let (sin_a, cos_a) = a.sin_cos();
+            Point::new(
+                cx + rx * cos_a * cos_phi - ry * sin_a * sin_phi,
+                cy + rx * cos_a * sin_phi + ry * sin_a * cos_phi,
+            )
+        };
+
+        let derivative_at = |a: f64| {
+            let (sin_a, cos_a) = a.sin_cos();
+            Point::new(
+                -rx * sin_a * cos_phi - ry * cos_a * sin_phi,
+                -rx * sin_a * sin_phi + ry * cos_a * cos_phi,
+            )
+        };
+
+        let mut res = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let a1 = theta1 + step * i as f64;
+            let a2 = a1 + step;
+
+            let p1 = point_at(a1);
+            let d1 = derivative_at(a1);
+            let p2 = if i == n - 1 { to } else { point_at(a2) };
+            let d2 = derivative_at(a2);
+
+            res.push((
+                Point::new(p1.x + t * d1.x, p1.y + t * d1.y),
+                Point::new(p2.x - t * d2.x, p2.y - t * d2.y),
+                p2,
+            ));
+        }
+
+        res
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum SegmentKind {
+    Line(Point),
+    QuadraticBezier(Point, Point),
+    CubicBezier(Point, Point, Point),
+    Arc(Arc),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Segment {
+    pub kind: SegmentKind,
+    pub is_stroked: bool,
+}
+
+impl Segment {
+    pub fn new(kind: SegmentKind) -> Self {
+        Self {
+            kind,
+            is_stroked: true,
+        }
+    }
+
+    /// Returns the point where the segment ends
+    pub fn end_point(&self) -> Point {
+        match self.kind {
+            SegmentKind::Line(p) => p,
+            SegmentKind::QuadraticBezier(_, p) => p,
+            SegmentKind::CubicBezier(_, _, p) => p,
+            SegmentKind::Arc(ref a) => a.point,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Figure {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+    pub is_closed: bool,
+    pub is_filled: bool,
+}
+
+impl Figure {
+    pub fn new(start: Point) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+            is_closed: false,
+            is_filled: true,
+        }
+    }
+}
+
+/// Receives the outline of a `Geometry` as lines and cubic bezier curves
+pub(crate) trait PathBuilder {
+    fn move_to(&mut self, p: Point);
+    fn line_to(&mut self, p: Point);
+    fn curve_to(&mut self, c1: Point, c2: Point, p: Point);
+    fn close(&mut self);
+}
+
+impl Figure {
+    /// Emits the figure to the builder. When building a stroke outline,
+    /// segments that are not stroked are replaced by a move.
+    fn build(&self, builder: &mut impl PathBuilder, stroke: bool) {
+        builder.move_to(self.start);
+
+        let mut current = self.start;
+        let mut all_stroked = true;
+
+        for segment in self.segments.iter() {
+            let end = segment.end_point();
+
+            if stroke && !segment.is_stroked {
+                builder.move_to(end);
+                current = end;
+                all_stroked = false;
+                continue;
+            }
+
+            match segment.kind {
+                SegmentKind::Line(p) => builder.line_to(p),
+                SegmentKind::QuadraticBezier(c, p) => {
+                    let c1 = Point::new(
+                        current.x + 2.0 / 3.0 * (c.x - current.x),
+                        current.y + 2.0 / 3.0 * (c.y - current.y),
+                    );
+                    let c2 =
+                        Point::new(p.x + 2.0 / 3.0 * (c.x - p.x), p.y + 2.0 / 3.0 * (c.y - p.y));
+                    builder.curve_to(c1, c2, p);
+                }
+                SegmentKind::CubicBezier(c1, c2, p) => builder.curve_to(c1, c2, p),
+                SegmentKind::Arc(arc) => {
+                    for (c1, c2, p) in arc.to_cubics(current) {
+                        builder.curve_to(c1, c2, p);
+                    }
+                }
+            }
+
+            current = end;
+        }
+
+        if self.is_closed {
+            if all_stroked {
+                builder.close();
+            } else {
+                builder.line_to(self.start);
+            }
+        }
+    }
+}
+
+/// Geometry of a path: a list of figures, each made of connected segments.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Geometry {
+    pub fill_rule: FillRule,
+    pub figures: Vec<Figure>,
+    pub transform: Option<RenderTransform>,
+}
+
+impl Geometry {
+    /// Emits the outline of the figures that should be filled
+    pub fn build_fill(&self, builder: &mut impl PathBuilder) {
+        for figure in self.figures.iter().filter(|f| f.is_filled) {
+            figure.build(builder, false);
+        }
+    }
+
+    /// Emits the outline of the segments that should be stroked
+    pub fn build_stroke(&self, builder: &mut impl PathBuilder) {
+        for figure in self.figures.iter() {
+            figure.build(builder, true);
+        }
+    }
+}
+
+/// Parses the abbreviated geometry syntax, see section 4.2.3
+/// of the XPS spec
+impl FromStr for Geometry {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PathDataParser::new(s).parse()
+    }
+}
+
+struct PathDataParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    geometry: Geometry,
+    figure: Option<Figure>,
+    current: Point,
+    last_cubic_control: Option<Point>,
+}
+
+impl<'a> PathDataParser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            data: s.as_bytes(),
+            pos: 0,
+            geometry: Geometry::default(),
+            figure: None,
+            current: Point::default(),
+            last_cubic_control: None,
+        }
+    }
+
+    fn parse(mut self) -> Result<Geometry, ParseError> {
+        self.skip_separators();
+
+        if self.peek() == Some(b'F') {
+            self.pos += 1;
+            self.skip_separators();
+            self.geometry.fill_rule = match self.peek() {
+                Some(b'0') => FillRule::EvenOdd,
+                Some(b'1') => FillRule::NonZero,
+                _ => return Err(ParseError::InvalidPathData),
+            };
+            self.pos += 1;
+        }
+
+        loop {
+            self.skip_separators();
+
+            let cmd = match self.peek() {
+                Some(c) if c.is_ascii_alphabetic() => c,
+                Some(_) => return Err(ParseError::InvalidPathData),
+                None => break,
+            };
+
+            self.pos += 1;
+
+            self.parse_command(cmd)?;
+        }
+
+        self.finish_figure();
+
+        Ok(self.geometry)
+    }
+
+    fn parse_command(&mut self, cmd: u8) -> Result<(), ParseError> {
+        let relative = cmd.is_ascii_lowercase();
+
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                let p = self.point(relative)?;
+                self.finish_figure();
+                self.figure = Some(Figure::new(p));
+                self.current = p;
+                self.last_cubic_control = None;
+
+                // Additional points are implicit line commands
+                while self.has_number() {
+                    let p = self.point(relative)?;
+                    self.line_to(p);
+                }
+            }
+            b'L' => loop {
+                let p = self.point(relative)?;
+                self.line_to(p);
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'H' => loop {
+                let mut x = self.number()?;
+                if relative {
+                    x += self.current.x;
+                }
+                let p = Point::new(x, self.current.y);
+                self.line_to(p);
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'V' => loop {
+                let mut y = self.number()?;
+                if relative {
+                    y += self.current.y;
+                }
+                let p = Point::new(self.current.x, y);
+                self.line_to(p);
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'C' => loop {
+                let c1 = self.point(relative)?;
+                let c2 = self.point(relative)?;
+                let p = self.point(relative)?;
+                self.push_segment(SegmentKind::CubicBezier(c1, c2, p));
+                self.last_cubic_control = Some(c2);
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'S' => loop {
+                // The first control point is the reflection of the second
+                // control point of the previous cubic bezier, if any
+                let c1 = match self.last_cubic_control {
+                    Some(c) => Point::new(2.0 * self.current.x - c.x, 2.0 * self.current.y - c.y),
+                    None => self.current,
+                };
+                let c2 = self.point(relative)?;
+                let p = self.point(relative)?;
+                self.push_segment(SegmentKind::CubicBezier(c1, c2, p));
+                self.last_cubic_control = Some(c2);
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'Q' => loop {
+                let c = self.point(relative)?;
+                let p = self.point(relative)?;
+                self.push_segment(SegmentKind::QuadraticBezier(c, p));
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'A' => loop {
+                let size = (self.number()?, self.number()?);
+                let rotation_angle = self.number()?;
+                let is_large_arc = self.flag()?;
+                let sweep_direction = if self.flag()? {
+                    SweepDirection::Clockwise
+                } else {
+                    SweepDirection::Counterclockwise
+                };
+                let point = self.point(relative)?;
+
+                self.push_segment(SegmentKind::Arc(Arc {
+                    point,
+                    size,
+                    rotation_angle,
+                    is_large_arc,
+                    sweep_direction,
+                }));
+
+                if !self.has_number() {
+                    break;
+                }
+            },
+            b'Z' => {
+                if let Some(mut figure) = self.figure.take() {
+                    figure.is_closed = true;
+                    self.current = figure.start;
+                    self.geometry.figures.push(figure);
+                }
+                self.last_cubic_control = None;
+            }
+            _ => return Err(ParseError::InvalidPathData),
+        }
+
+        Ok(())
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.push_segment(SegmentKind::Line(p));
+    }
+
+    fn push_segment(&mut self, kind: SegmentKind) {
+        let segment = Segment::new(kind);
+        let current = self.current;
+
+        // A drawing command after a close starts a new figure
+        // from the current point
+        self.figure
+            .get_or_insert_with(|| Figure::new(current))
+            .segments
+            .push(segment);
+
+        self.current = segment.end_point();
+        self.last_cubic_control = None;
+    }
+
+    fn finish_figure(&mut self) {
+        if let Some(figure) = self.figure.take() {
+            if !figure.segments.is_empty() {
+                self.geometry.figures.push(figure);
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() || c == b',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+
+        match self.peek() {
+            Some(c) => c.is_ascii_digit() || c == b'-' || c == b'+' || c == b'.',
+            None => false,
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        self.skip_separators();
+
+        let start = self.pos;
+
+        if let Some(b'-') | Some(b'+') = self.peek() {
+            self.pos += 1;
+        }
+
+        let int_digits = self.skip_digits();
+        let mut frac_digits = 0;
+
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            frac_digits = self.skip_digits();
+        }
+
+        if int_digits + frac_digits == 0 {
+            return Err(ParseError::InvalidPathData);
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            let mantissa_end = self.pos;
+
+            self.pos += 1;
+            if let Some(b'-') | Some(b'+') = self.peek() {
+                self.pos += 1;
+            }
+
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+
+        // Only ASCII characters were consumed
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .ok_or(ParseError::InvalidPathData)
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.pos - start
+    }
+
+    fn flag(&mut self) -> Result<bool, ParseError> {
+        self.number().map(|n| n != 0.0)
+    }
+
+    fn point(&mut self, relative: bool) -> Result<Point, ParseError> {
+        let x = self.number()?;
+        let y = self.number()?;
+
+        if relative {
+            Ok(Point::new(self.current.x + x, self.current.y + y))
+        } else {
+            Ok(Point::new(x, y))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Geometry {
+        s.parse::<Geometry>().unwrap()
+    }
+
+    fn points(figure: &Figure) -> Vec<Point> {
+        figure.segments.iter().map(Segment::end_point).collect()
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(
+            (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    fn p(x: f64, y: f64) -> Point {
+        Point::new(x, y)
+    }
+
+    #[test]
+    fn move_and_line() {
+        let g = parse("M 10,20 L 30,40 l 5,5");
+        assert_eq!(g.figures.len(), 1);
+        assert_eq!(g.figures[0].start, p(10.0, 20.0));
+        assert_eq!(points(&g.figures[0]), vec![p(30.0, 40.0), p(35.0, 45.0)]);
+
+        let g = parse("m 1,1 l 1,0 m 1,1 l 0,1");
+        assert_eq!(g.figures.len(), 2);
+        assert_eq!(g.figures[1].start, p(3.0, 2.0));
+        assert_eq!(points(&g.figures[1]), vec![p(3.0, 3.0)]);
+    }
+
+    #[test]
+    fn horizontal_and_vertical() {
+        let g = parse("M 1,1 H 5 V 7 h -2 v -3");
+        assert_eq!(
+            points(&g.figures[0]),
+            vec![p(5.0, 1.0), p(5.0, 7.0), p(3.0, 7.0), p(3.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn implicit_repeats() {
+        // Points after a move are lines
+        let g = parse("M 0,0 10,0 10,10");
+        assert_eq!(points(&g.figures[0]), vec![p(10.0, 0.0), p(10.0, 10.0)]);
+
+        let g = parse("m 1,1 1,0 0,1");
+        assert_eq!(points(&g.figures[0]), vec![p(2.0, 1.0), p(2.0, 2.0)]);
+
+        let g = parse("M 0,0 L 1,1 2,2 H 3 4 V 5 6");
+        assert_eq!(
+            points(&g.figures[0]),
+            vec![
+                p(1.0, 1.0),
+                p(2.0, 2.0),
+                p(3.0, 2.0),
+                p(4.0, 2.0),
+                p(4.0, 5.0),
+                p(4.0, 6.0)
+            ]
+        );
+
+        let g = parse("M 0,0 c 1,1 2,1 3,0 1,1 2,1 3,0");
+        assert_eq!(points(&g.figures[0]), vec![p(3.0, 0.0), p(6.0, 0.0)]);
+    }
+
+    #[test]
+    fn cubic_bezier() {
+        let g = parse("M 0,0 C 1,1 2,1 3,0 c 1,1 2,1 3,0");
+        let segments = &g.figures[0].segments;
+
+        match segments[0].kind {
+            SegmentKind::CubicBezier(c1, c2, e) => {
+                assert_eq!((c1, c2, e), (p(1.0, 1.0), p(2.0, 1.0), p(3.0, 0.0)))
+            }
+            ref k => panic!("unexpected segment {:?}", k),
+        }
+        match segments[1].kind {
+            SegmentKind::CubicBezier(c1, c2, e) => {
+                assert_eq!((c1, c2, e), (p(4.0, 1.0), p(5.0, 1.0), p(6.0, 0.0)))
+            }
+            ref k => panic!("unexpected segment {:?}", k),
+        }
+    }
+
+    #[test]
+    fn smooth_cubic_bezier() {
+        for data in &[
+            "M 0,0 C 1,1 2,1 3,0 S 5,-1 6,0",
+            "M 0,0 c 1,1 2,1 3,0 s 2,-1 3,0",
+        ] {
+            let g = parse(data);
+            match g.figures[0].segments[1].kind {
+                SegmentKind::CubicBezier(c1, c2, e) => {
+                    assert_eq!((c1, c2, e), (p(4.0, -1.0), p(5.0, -1.0), p(6.0, 0.0)))
+                }
+                ref k => panic!("unexpected segment {:?}", k),
+            }
+        }
+
+        // Without a previous cubic, the first control point is the
+        // current point
+        let g = parse("M 1,1 L 2,2 S 3,3 4,2");
+        match g.figures[0].segments[1].kind {
+            SegmentKind::CubicBezier(c1, _, _) => assert_eq!(c1, p(2.0, 2.0)),
+            ref k => panic!("unexpected segment {:?}", k),
+        }
+    }
+
+    #[test]
+    fn quadratic_bezier() {
+        let g = parse("M 0,0 Q 1,1 2,0 q 1,1 2,0");
+        match g.figures[0].segments[1].kind {
+            SegmentKind::QuadraticBezier(c, e) => assert_eq!((c, e), (p(3.0, 1.0), p(4.0, 0.0))),
+            ref k => panic!("unexpected segment {:?}", k),
+        }
+    }
+
+    #[test]
+    fn arc() {
+        let g = parse("M 0,0 A 1,2 30 1 0 5,5 a 1,1 0 0 1 1,1");
+        let arcs = g.figures[0]
+            .segments
+            .iter()
+            .map(|s| match s.kind {
+                SegmentKind::Arc(a) => a,
+                ref k => panic!("unexpected segment {:?}", k),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(arcs[0].size, (1.0, 2.0));
+        assert_eq!(arcs[0].rotation_angle, 30.0);
+        assert!(arcs[0].is_large_arc);
+        assert_eq!(arcs[0].sweep_direction, SweepDirection::Counterclockwise);
+        assert_eq!(arcs[0].point, p(5.0, 5.0));
+
+        assert!(!arcs[1].is_large_arc);
+        assert_eq!(arcs[1].sweep_direction, SweepDirection::Clockwise);
+        assert_eq!(arcs[1].point, p(6.0, 6.0));
+    }
+
+    #[test]
+    fn close() {
+        let g = parse("M 1,1 L 2,1 Z l 0,1 z");
+        assert_eq!(g.figures.len(), 2);
+        assert!(g.figures[0].is_closed);
+
+        // Drawing after a close starts from the start of the figure
+        assert_eq!(g.figures[1].start, p(1.0, 1.0));
+        assert_eq!(points(&g.figures[1]), vec![p(1.0, 2.0)]);
+        assert!(g.figures[1].is_closed);
+    }
+
+    #[test]
+    fn fill_rule() {
+        assert_eq!(parse("M 0,0 L 1,1").fill_rule, FillRule::EvenOdd);
+        assert_eq!(parse("F0 M 0,0 L 1,1").fill_rule, FillRule::EvenOdd);
+        assert_eq!(parse("F 1 M 0,0 L 1,1").fill_rule, FillRule::NonZero);
+        assert!("F2 M 0,0 L 1,1".parse::<Geometry>().is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        let g = parse("M1e2,1.5E-1L-.5e+1,+2 .5,-3.");
+        assert_eq!(g.figures[0].start, p(100.0, 0.15));
+        assert_eq!(points(&g.figures[0]), vec![p(-5.0, 2.0), p(0.5, -3.0)]);
+
+        // An exponent without digits is not part of the number
+        assert!("M 1e,2".parse::<Geometry>().is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        for data in &["M 0,0 L", "M 1", "X 1,1", "M 0,0 L 1,1 #", "M 0,0 L -,1"] {
+            assert!(data.parse::<Geometry>().is_err(), "{} was parsed", data);
+        }
+    }
+
+    #[test]
+    fn arc_to_cubics() {
+        let arc = |size: (f64, f64), is_large_arc: bool, sweep_direction: SweepDirection| Arc {
+            point: p(2.0, 0.0),
+            size,
+            rotation_angle: 0.0,
+            is_large_arc,
+            sweep_direction,
+        };
+
+        // Half circles, clockwise above the chord in the y-down space
+        let cubics = arc((1.0, 1.0), false, SweepDirection::Clockwise).to_cubics(p(0.0, 0.0));
+        assert_eq!(cubics.len(), 2);
+        assert_near(cubics[0].2, p(1.0, -1.0));
+        assert_near(cubics[1].2, p(2.0, 0.0));
+
+        let cubics =
+            arc((1.0, 1.0), false, SweepDirection::Counterclockwise).to_cubics(p(0.0, 0.0));
+        assert_near(cubics[0].2, p(1.0, 1.0));
+
+        // Radii too small to reach the end point are scaled up
+        let cubics = arc((0.5, 0.5), false, SweepDirection::Clockwise).to_cubics(p(0.0, 0.0));
+        assert_near(cubics[0].2, p(1.0, -1.0));
+
+        // The large arc goes around the center on the other side
+        let sqrt3 = 3f64.sqrt();
+        for &(is_large_arc, center) in &[(false, p(1.0, sqrt3)), (true, p(1.0, -sqrt3))] {
+            let cubics =
+                arc((2.0, 2.0), is_large_arc, SweepDirection::Clockwise).to_cubics(p(0.0, 0.0));
+            assert_eq!(cubics.len(), if is_large_arc { 4 } else { 1 });
+
+            for &(_, _, e) in cubics.iter() {
+                let r = ((e.x - center.x).powi(2) + (e.y - center.y).powi(2)).sqrt();
+                assert!((r - 2.0).abs() < 1e-9);
+            }
+        }
+
+        // Degenerate arcs
+        let cubics = arc((0.0, 1.0), true, SweepDirection::Clockwise).to_cubics(p(0.0, 0.0));
+        assert_eq!(cubics, vec![(p(0.0, 0.0), p(2.0, 0.0), p(2.0, 0.0))]);
+        assert!(arc((1.0, 1.0), true, SweepDirection::Clockwise)
+            .to_cubics(p(2.0, 0.0))
+            .is_empty());
+    }
+}
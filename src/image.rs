@@ -0,0 +1,49 @@
+/// A raster image decoded from an image part of the package
+pub(crate) struct Image {
+    width: u32,
+    height: u32,
+    dpi: (f64, f64),
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Returns an image with the given RGBA pixels, not premultiplied,
+    /// and resolution
+    pub fn new(width: u32, height: u32, dpi: (f64, f64), data: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            dpi,
+            data,
+        }
+    }
+
+    /// Returns the size of the image in pixels
+    pub fn pixel_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the size of the image in 1/96 inch, the units
+    /// of the viewbox of an image brush
+    pub fn size(&self) -> (f64, f64) {
+        (
+            f64::from(self.width) * 96.0 / self.dpi.0,
+            f64::from(self.height) * 96.0 / self.dpi.1,
+        )
+    }
+
+    /// Returns the RGBA pixels of the image, not premultiplied
+    pub fn pixels(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("dpi", &self.dpi)
+            .finish()
+    }
+}
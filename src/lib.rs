@@ -9,17 +9,47 @@
 )]
 
 mod archive;
+mod brush;
+mod color;
+mod content_types;
 mod error;
+mod font;
+mod geometry;
+mod image;
 mod parts;
 mod relationships;
 mod renderer;
+mod resources;
+mod text;
 mod xps;
 
 pub use crate::renderer::Renderer;
-pub use crate::xps::XPS;
+pub use crate::text::TextRun;
+pub use crate::xps::{Document, Outline, OutlineEntry, Page, XPS};
 
 #[cfg(feature = "cairo-renderer")]
 mod cairo_renderer;
 
 #[cfg(feature = "cairo-renderer")]
 pub use crate::cairo_renderer::CairoRenderer;
+
+#[cfg(feature = "pdf")]
+mod cairo_font;
+
+#[cfg(feature = "pdf")]
+mod pdf;
+
+#[cfg(feature = "pdf")]
+pub use crate::pdf::PdfWriter;
+
+#[cfg(feature = "svg-renderer")]
+mod svg_renderer;
+
+#[cfg(feature = "svg-renderer")]
+pub use crate::svg_renderer::SvgRenderer;
+
+#[cfg(feature = "tiny-skia-renderer")]
+mod tiny_skia_renderer;
+
+#[cfg(feature = "tiny-skia-renderer")]
+pub use crate::tiny_skia_renderer::TinySkiaRenderer;
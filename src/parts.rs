@@ -1,12 +1,28 @@
+use std::cmp::Ordering;
 use std::path::PathBuf;
 
+use log::warn;
+
+use crate::brush::{
+    Brush, ColorInterpolationMode, Gradient, GradientStop, ImageBrush, LinearGradientBrush,
+    MappingMode, RadialGradientBrush, SolidColorBrush, SpreadMethod, TileBrush, TileMode,
+    VisualBrush,
+};
+use crate::color::Color;
+use crate::content_types;
 use crate::error::{ParseError, ParseResult, Result};
+use crate::geometry::{
+    Arc, Figure, FillRule, Geometry, Point, Segment, SegmentKind, SweepDirection,
+};
+use crate::image::Image;
 use crate::renderer::{
-    BleedBox, Brush, Canvas, Clip, ContentBox, EdgeMode, Fill, Glyphs, Indices, IsSideways,
-    NavigateUri, Opacity, OpacityMask, Path, RenderNode, RenderTransform, Stroke, StrokeDashArray,
-    StrokeDashOffset, StrokeEndLineCap, StrokeLineJoin, StrokeStartLineCap, StyleSimulations,
+    BidiLevel, BleedBox, Canvas, CaretStops, Clip, ContentBox, DeviceFontName, EdgeMode, Fill,
+    Glyphs, Indices, IsSideways, LineCap, NavigateUri, Opacity, OpacityMask, Path, Rect,
+    RenderNode, RenderTransform, Stroke, StrokeDashArray, StrokeDashOffset, StrokeEndLineCap,
+    StrokeLineJoin, StrokeMiterLimit, StrokeStartLineCap, StrokeThickness, StyleSimulations,
     UnicodeString,
 };
+use crate::resources::{DictionaryLoader, Resource, Resources};
 use crate::xps::{Outline, OutlineEntry};
 
 /*
@@ -14,11 +30,11 @@ use crate::xps::{Outline, OutlineEntry};
     FixedDocument,
     FixedPage,
     DocumentStructure,
-
-Not implemented:
     Font,
     Image,
     RemoteResourceDictionary,
+
+Not implemented:
     Thumbnail,
     PrintTicket,
     ICCProfile,
@@ -32,6 +48,8 @@ mod ns {
     pub const XPS: &str = "http://schemas.microsoft.com/xps/2005/06";
     pub const OXPS: &str = "http://schemas.openxps.org/oxps/v1.0";
     pub const DOC_STRUCT: &str = "http://schemas.microsoft.com/xps/2005/06/documentstructure";
+    pub const RESOURCE_KEY: &str = "http://schemas.microsoft.com/xps/2005/06/resourcedictionary-key";
+    pub const OXPS_RESOURCE_KEY: &str = "http://schemas.openxps.org/oxps/v1.0/resourcedictionary-key";
 }
 
 #[derive(Debug, Default)]
@@ -71,8 +89,8 @@ pub struct FixedDocument;
 #[derive(Debug, Default)]
 pub struct FixedDocumentPage {
     pub(crate) source: Option<String>,
-    pub(crate) width: f64,
-    pub(crate) height: f64,
+    pub(crate) width: Option<f64>,
+    pub(crate) height: Option<f64>,
     pub(crate) links: Vec<String>,
 }
 
@@ -95,13 +113,8 @@ impl FixedDocument {
 
                 page.source = node.attribute("Source").map(String::from);
 
-                if let Some(w) = node.attribute("Width") {
-                    page.width = parse_size(w);
-                }
-
-                if let Some(h) = node.attribute("Height") {
-                    page.height = parse_size(h);
-                }
+                page.width = node.attribute("Width").map(parse_size);
+                page.height = node.attribute("Height").map(parse_size);
 
                 for node in node
                     .children()
@@ -137,7 +150,9 @@ pub struct FixedPage {
 }
 
 impl FixedPage {
-    pub fn parse(fixed_page: &str) -> Result<Self> {
+    /// Parses the page, using `load_dictionary` to load the remote
+    /// resource dictionaries it references
+    pub fn parse(fixed_page: &str, load_dictionary: &DictionaryLoader<'_>) -> Result<Self> {
         let doc = roxmltree::Document::parse(fixed_page)?;
 
         let mut page = FixedPage::default();
@@ -156,7 +171,10 @@ impl FixedPage {
             // ContentBox
             // BleedBox
 
-            parse_render_node(node, &mut page.render_tree)?;
+            let root = Resources::with_loader(load_dictionary);
+            let resources = parse_resources(node, "FixedPage.Resources", &root)?;
+
+            parse_render_node(node, &mut page.render_tree, &resources)?;
         }
 
         Ok(page)
@@ -166,50 +184,62 @@ impl FixedPage {
 fn parse_render_node<'a, 'i: 'a>(
     xml_node: roxmltree::Node<'a, 'i>,
     render_node: &mut RenderNode,
-) -> Result<()> {
+    resources: &Resources<'_>,
+) -> ParseResult<()> {
     for n in xml_node.children() {
-        if has_xps_tag_name(&n, "Path") {
-            let path = parse_path(n)?;
-            render_node.append(RenderNode::Path(path))
-        } else if has_xps_tag_name(&n, "Glyphs") {
-            let glyphs = parse_glyphs(n)?;
-            render_node.append(RenderNode::Glyphs(glyphs))
-        } else if has_xps_tag_name(&n, "Canvas") {
-            let canvas = parse_canvas(n)?;
-
-            // Canvas is a group that contains Path, Glyphs and Canvas
-            let mut canvas = RenderNode::Canvas(canvas);
-            parse_render_node(n, &mut canvas)?;
-
-            render_node.append(canvas)
+        if let Some(node) = parse_visual(n, resources)? {
+            render_node.append(node);
         }
     }
 
     Ok(())
 }
 
-fn parse_canvas<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Canvas> {
+// Parses a Path, Glyphs or Canvas element
+fn parse_visual<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Option<RenderNode>> {
+    if has_xps_tag_name(&node, "Path") {
+        parse_path(node, resources).map(|p| Some(RenderNode::Path(p)))
+    } else if has_xps_tag_name(&node, "Glyphs") {
+        parse_glyphs(node, resources).map(|g| Some(RenderNode::Glyphs(g)))
+    } else if has_xps_tag_name(&node, "Canvas") {
+        // The resources of a canvas are in scope for its own
+        // properties and for its children
+        let resources = parse_resources(node, "Canvas.Resources", resources)?;
+
+        let canvas = parse_canvas(node, &resources)?;
+
+        // Canvas is a group that contains Path, Glyphs and Canvas
+        let mut canvas = RenderNode::Canvas(canvas);
+        parse_render_node(node, &mut canvas, &resources)?;
+
+        Ok(Some(canvas))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_canvas<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Canvas> {
     let mut canvas = Canvas::default();
 
     canvas.name = node.attribute("Name").map(String::from);
 
     // FIXME: xml:lang - how does roxmltree deal with attribute namespaces?
 
-    // FIXME: x:Key - how does roxmltree deal with attribute namespaces?
+    canvas.render_transform = parse_transform_attribute(node, "RenderTransform", resources)?;
 
-    canvas.render_transform = node
-        .attribute("RenderTransform")
-        .and_then(|s| s.parse::<RenderTransform>().ok());
-
-    canvas.clip = node.attribute("Clip").and_then(|s| s.parse::<Clip>().ok());
+    canvas.clip = parse_geometry_attribute(node, "Clip", resources)?.map(Clip);
 
     canvas.opacity = node
         .attribute("Opacity")
         .and_then(|s| s.parse::<Opacity>().ok());
 
-    canvas.opacity_mask = node
-        .attribute("OpacityMask")
-        .and_then(|s| s.parse::<OpacityMask>().ok());
+    canvas.opacity_mask = parse_brush_attribute(node, "OpacityMask", resources)?.map(OpacityMask);
 
     canvas.edge_mode = node
         .attribute("RenderOptions.EdgeMode")
@@ -224,29 +254,28 @@ fn parse_canvas<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Canvas
     // AutomationProperties.HelpText
 
     for n in node.children() {
-        if has_xps_tag_name(&n, "Canvas.Resources") {
-            parse_resources(n)?;
-        } else if has_xps_tag_name(&n, "Canvas.RenderTransform") {
-            parse_render_transform(n)?;
+        if has_xps_tag_name(&n, "Canvas.RenderTransform") {
+            canvas.render_transform = parse_render_transform(node, n)?;
         } else if has_xps_tag_name(&n, "Canvas.Clip") {
-            parse_clip(n)?;
+            canvas.clip = parse_clip(node, n)?;
         } else if has_xps_tag_name(&n, "Canvas.OpacityMask") {
-            parse_opacity_mask(n)?;
+            canvas.opacity_mask = Some(parse_opacity_mask(n, resources)?);
         }
     }
 
     Ok(canvas)
 }
 
-fn parse_glyphs<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Glyphs> {
+fn parse_glyphs<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Glyphs> {
     let mut glyphs = Glyphs::default();
 
     glyphs.name = node.attribute("Name").map(String::from);
 
     // FIXME: xml:lang - how does roxmltree deal with attribute namespaces?
 
-    // FIXME: x:Key - how does roxmltree deal with attribute namespaces?
-
     glyphs.origin.0 = node
         .attribute("OriginX")
         .and_then(|s| s.parse::<f64>().ok())
@@ -264,8 +293,6 @@ fn parse_glyphs<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Glyphs
         glyphs.font_rendering_em_size = parse_size(s);
     }
 
-    /* TODO:
-
     glyphs.bidi_level = node
         .attribute("BidiLevel")
         .and_then(|s| s.parse::<BidiLevel>().ok());
@@ -278,9 +305,7 @@ fn parse_glyphs<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Glyphs
         .attribute("DeviceFontName")
         .and_then(|s| s.parse::<DeviceFontName>().ok());
 
-    */
-
-    glyphs.fill = node.attribute("Fill").and_then(|s| s.parse::<Fill>().ok());
+    glyphs.fill = parse_brush_attribute(node, "Fill", resources)?.map(Fill);
 
     glyphs.is_sideways = node
         .attribute("IsSideways")
@@ -294,23 +319,36 @@ fn parse_glyphs<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Glyphs
         .attribute("UnicodeString")
         .and_then(|s| s.parse::<UnicodeString>().ok());
 
+    // The hex digits pad the stops to a multiple of four, only the
+    // stops of the code units of the UnicodeString and the final one
+    // are kept
+    if let Some(caret_stops) = glyphs.caret_stops.as_mut() {
+        let units = glyphs
+            .unicode_string
+            .as_ref()
+            .map_or(0, |s| s.0.encode_utf16().count());
+
+        if caret_stops.0.len() > units {
+            caret_stops.0.truncate(units + 1);
+        } else {
+            warn!("Ignoring CaretStops shorter than the UnicodeString");
+            glyphs.caret_stops = None;
+        }
+    }
+
     glyphs.style_simulations = node
         .attribute("StyleSimulations")
         .and_then(|s| s.parse::<StyleSimulations>().ok());
 
-    glyphs.render_transform = node
-        .attribute("RenderTransform")
-        .and_then(|s| s.parse::<RenderTransform>().ok());
+    glyphs.render_transform = parse_transform_attribute(node, "RenderTransform", resources)?;
 
-    glyphs.clip = node.attribute("Clip").and_then(|s| s.parse::<Clip>().ok());
+    glyphs.clip = parse_geometry_attribute(node, "Clip", resources)?.map(Clip);
 
     glyphs.opacity = node
         .attribute("Opacity")
         .and_then(|s| s.parse::<Opacity>().ok());
 
-    glyphs.opacity_mask = node
-        .attribute("OpacityMask")
-        .and_then(|s| s.parse::<OpacityMask>().ok());
+    glyphs.opacity_mask = parse_brush_attribute(node, "OpacityMask", resources)?.map(OpacityMask);
 
     glyphs.navigate_uri = node
         .attribute("FixedPage.NavigateUri")
@@ -318,49 +356,44 @@ fn parse_glyphs<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Glyphs
 
     for n in node.children() {
         if has_xps_tag_name(&n, "Glyphs.RenderTransform") {
-            parse_render_transform(n)?;
-        } else if has_xps_tag_name(&n, "Glyps.Clip") {
-            parse_clip(n)?;
-        } else if has_xps_tag_name(&n, "Glyps.Fill") {
-            parse_fill(n)?;
-        } else if has_xps_tag_name(&n, "Glyps.OpacityMask") {
-            parse_opacity_mask(n)?;
+            glyphs.render_transform = parse_render_transform(node, n)?;
+        } else if has_xps_tag_name(&n, "Glyphs.Clip") {
+            glyphs.clip = parse_clip(node, n)?;
+        } else if has_xps_tag_name(&n, "Glyphs.Fill") {
+            glyphs.fill = Some(parse_fill(n, resources)?);
+        } else if has_xps_tag_name(&n, "Glyphs.OpacityMask") {
+            glyphs.opacity_mask = Some(parse_opacity_mask(n, resources)?);
         }
     }
 
     Ok(glyphs)
 }
 
-fn parse_path<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Path> {
+fn parse_path<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Path> {
     let mut path = Path::default();
 
     path.name = node.attribute("Name").map(String::from);
 
     // FIXME: xml:lang - how does roxmltree deal with attribute namespaces?
 
-    // FIXME: x:Key - how does roxmltree deal with attribute namespaces?
-
-    path.data = node.attribute("Data").map(String::from);
+    path.data = parse_geometry_attribute(node, "Data", resources)?;
 
-    path.fill = node.attribute("Fill").and_then(|s| s.parse::<Fill>().ok());
+    path.fill = parse_brush_attribute(node, "Fill", resources)?.map(Fill);
 
-    path.render_transform = node
-        .attribute("RenderTransform")
-        .and_then(|s| s.parse::<RenderTransform>().ok());
+    path.render_transform = parse_transform_attribute(node, "RenderTransform", resources)?;
 
-    path.clip = node.attribute("Clip").and_then(|s| s.parse::<Clip>().ok());
+    path.clip = parse_geometry_attribute(node, "Clip", resources)?.map(Clip);
 
     path.opacity = node
         .attribute("Opacity")
         .and_then(|s| s.parse::<Opacity>().ok());
 
-    path.opacity_mask = node
-        .attribute("OpacityMask")
-        .and_then(|s| s.parse::<OpacityMask>().ok());
+    path.opacity_mask = parse_brush_attribute(node, "OpacityMask", resources)?.map(OpacityMask);
 
-    path.stroke = node
-        .attribute("Stroke")
-        .and_then(|s| s.parse::<Stroke>().ok());
+    path.stroke = parse_brush_attribute(node, "Stroke", resources)?.map(Stroke);
 
     path.stroke_dash_array = node
         .attribute("StrokeDashArray")
@@ -382,114 +415,809 @@ fn parse_path<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Path> {
         .attribute("StrokeLineJoin")
         .and_then(|s| s.parse::<StrokeLineJoin>().ok());
 
+    path.stroke_miter_limit = node
+        .attribute("StrokeMiterLimit")
+        .and_then(|s| s.parse::<StrokeMiterLimit>().ok());
+
+    path.stroke_thickness = node
+        .attribute("StrokeThickness")
+        .and_then(|s| s.parse::<StrokeThickness>().ok());
+
+    path.navigate_uri = node
+        .attribute("FixedPage.NavigateUri")
+        .and_then(|s| s.parse::<NavigateUri>().ok());
+
     for n in node.children() {
         if has_xps_tag_name(&n, "Path.Data") {
-            parse_path_data(n)?;
+            path.data = parse_path_data(n);
         } else if has_xps_tag_name(&n, "Path.RenderTransform") {
-            parse_render_transform(n)?;
+            path.render_transform = parse_render_transform(node, n)?;
         } else if has_xps_tag_name(&n, "Path.Clip") {
-            parse_clip(n)?;
+            path.clip = parse_clip(node, n)?;
         } else if has_xps_tag_name(&n, "Path.Fill") {
-            parse_fill(n)?;
+            path.fill = Some(parse_fill(n, resources)?);
         } else if has_xps_tag_name(&n, "Path.Stroke") {
-            parse_stroke(n)?;
+            path.stroke = Some(parse_stroke(n, resources)?);
         } else if has_xps_tag_name(&n, "Path.OpacityMask") {
-            parse_opacity_mask(n)?;
+            path.opacity_mask = Some(parse_opacity_mask(n, resources)?);
         }
     }
 
+    if path.stroke.is_some() {
+        warn_line_caps(&path);
+    }
+
     Ok(path)
 }
 
-fn parse_render_transform<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
-}
+// The renderers draw the start cap at both ends of the lines, and
+// triangle caps as round caps
+fn warn_line_caps(path: &Path) {
+    let start_cap = path.stroke_start_line_cap.map(|c| c.0).unwrap_or_default();
+    let end_cap = path.stroke_end_line_cap.map(|c| c.0).unwrap_or_default();
+
+    if end_cap != start_cap {
+        warn!(
+            "StrokeEndLineCap {:?} differs from StrokeStartLineCap {:?}, drawing {:?} caps",
+            end_cap, start_cap, start_cap
+        );
+    }
 
-fn parse_clip<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+    if start_cap == LineCap::Triangle {
+        warn!("Triangle line caps are drawn as round caps");
+    }
 }
 
-fn parse_fill<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    let _brush = parse_brush(node)?;
+// The transform of an element is either given by the attribute or by
+// the property element, specifying both is an error
+fn parse_render_transform<'a, 'i: 'a>(
+    element: roxmltree::Node<'a, 'i>,
+    property: roxmltree::Node<'a, 'i>,
+) -> ParseResult<Option<RenderTransform>> {
+    if element.attribute("RenderTransform").is_some() {
+        return Err(ParseError::DuplicateProperty(String::from(
+            "RenderTransform",
+        )));
+    }
 
-    // TODO
-    Ok(())
+    parse_matrix_transform(property)
 }
 
-fn parse_stroke<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    let _brush = parse_brush(node)?;
+fn parse_clip<'a, 'i: 'a>(
+    element: roxmltree::Node<'a, 'i>,
+    property: roxmltree::Node<'a, 'i>,
+) -> ParseResult<Option<Clip>> {
+    if element.attribute("Clip").is_some() {
+        return Err(ParseError::DuplicateProperty(String::from("Clip")));
+    }
 
-    // TODO
-    Ok(())
+    // Like the path data, the clip is a PathGeometry element
+    Ok(parse_path_data(property).map(Clip))
 }
 
-fn parse_opacity_mask<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    let _brush = parse_brush(node)?;
+fn parse_fill<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Fill> {
+    parse_brush(node, resources).map(Fill)
+}
 
-    // TODO
-    Ok(())
+fn parse_stroke<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Stroke> {
+    parse_brush(node, resources).map(Stroke)
 }
 
-fn parse_brush<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Brush> {
+fn parse_opacity_mask<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<OpacityMask> {
+    parse_brush(node, resources).map(OpacityMask)
+}
+
+fn parse_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Brush> {
     for n in node.children() {
-        if has_xps_tag_name(&n, "ImageBrush") {
-            parse_image_brush(n)?;
-            return Ok(Brush::Image);
-        } else if has_xps_tag_name(&n, "LinearGradientBrush") {
-            parse_linear_gradient_brush(n)?;
-            return Ok(Brush::LinearGradient);
-        } else if has_xps_tag_name(&n, "RadialGradientBrush") {
-            parse_radial_gradient_brush(n)?;
-            return Ok(Brush::RadialGradient);
-        } else if has_xps_tag_name(&n, "SolidColorBrush") {
-            parse_solid_color_brush(n)?;
-            return Ok(Brush::SolidColor);
-        } else if has_xps_tag_name(&n, "VisualBrush") {
-            parse_visual_brush(n)?;
-            return Ok(Brush::Visual);
+        if let Some(brush) = parse_brush_element(n, resources)? {
+            return Ok(brush);
         }
     }
 
     Err(ParseError::MissingBrush)
 }
 
-fn parse_image_brush<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+fn parse_brush_element<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Option<Brush>> {
+    let brush = if has_xps_tag_name(&node, "ImageBrush") {
+        Brush::Image(parse_image_brush(node)?)
+    } else if has_xps_tag_name(&node, "LinearGradientBrush") {
+        Brush::LinearGradient(parse_linear_gradient_brush(node)?)
+    } else if has_xps_tag_name(&node, "RadialGradientBrush") {
+        Brush::RadialGradient(parse_radial_gradient_brush(node)?)
+    } else if has_xps_tag_name(&node, "SolidColorBrush") {
+        Brush::SolidColor(parse_solid_color_brush(node)?)
+    } else if has_xps_tag_name(&node, "VisualBrush") {
+        Brush::Visual(parse_visual_brush(node, resources)?)
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(brush))
 }
 
-fn parse_linear_gradient_brush<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+fn parse_image_brush<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<ImageBrush> {
+    let tile = parse_tile_brush(node, "ImageBrush")?;
+
+    let image_source = node
+        .attribute("ImageSource")
+        .and_then(parse_image_source)
+        .ok_or(ParseError::InvalidBrush)?;
+
+    Ok(ImageBrush {
+        tile,
+        image_source,
+        image: None,
+    })
 }
 
-fn parse_radial_gradient_brush<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+// The image source is either the URI of the image part, or
+// "{ColorConvertedBitmap <image> <profile>}" to specify the color
+// profile of the image
+fn parse_image_source(s: &str) -> Option<String> {
+    let s = s.trim();
+
+    match s.strip_prefix('{') {
+        Some(ext) => {
+            let mut args = ext.strip_suffix('}')?.split_whitespace();
+            if args.next()? != "ColorConvertedBitmap" {
+                return None;
+            }
+
+            // FIXME: the color profile is ignored
+            args.next().map(String::from)
+        }
+        None => Some(s.to_string()),
+    }
 }
 
-fn parse_solid_color_brush<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+fn parse_linear_gradient_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+) -> ParseResult<LinearGradientBrush> {
+    let gradient = parse_gradient(node, "LinearGradientBrush")?;
+
+    let start_point = node
+        .attribute("StartPoint")
+        .ok_or(ParseError::InvalidBrush)
+        .and_then(parse_brush_point)?;
+
+    let end_point = node
+        .attribute("EndPoint")
+        .ok_or(ParseError::InvalidBrush)
+        .and_then(parse_brush_point)?;
+
+    Ok(LinearGradientBrush {
+        gradient,
+        start_point,
+        end_point,
+    })
 }
 
-fn parse_visual_brush<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+fn parse_radial_gradient_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+) -> ParseResult<RadialGradientBrush> {
+    let gradient = parse_gradient(node, "RadialGradientBrush")?;
+
+    let center = node
+        .attribute("Center")
+        .ok_or(ParseError::InvalidBrush)
+        .and_then(parse_brush_point)?;
+
+    let gradient_origin = node
+        .attribute("GradientOrigin")
+        .ok_or(ParseError::InvalidBrush)
+        .and_then(parse_brush_point)?;
+
+    let radius_x = node
+        .attribute("RadiusX")
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or(ParseError::InvalidBrush)?;
+
+    let radius_y = node
+        .attribute("RadiusY")
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or(ParseError::InvalidBrush)?;
+
+    Ok(RadialGradientBrush {
+        gradient,
+        center,
+        gradient_origin,
+        radius_x,
+        radius_y,
+    })
 }
 
-fn parse_resources<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
-    Ok(())
+fn parse_solid_color_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+) -> ParseResult<SolidColorBrush> {
+    let opacity = parse_brush_opacity(node);
+
+    let color = node
+        .attribute("Color")
+        .and_then(|s| s.parse::<Color>().ok())
+        .ok_or(ParseError::InvalidBrush)?;
+
+    Ok(SolidColorBrush { opacity, color })
+}
+
+fn parse_visual_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<VisualBrush> {
+    let tile = parse_tile_brush(node, "VisualBrush")?;
+
+    // The visual is a Canvas, Path or Glyphs element
+    let mut visual = RenderNode::Root(Vec::new());
+
+    if let Some(key) = node.attribute("Visual").and_then(static_resource_key) {
+        visual.append(resources.visual(key)?);
+    }
+
+    for n in node.children() {
+        if has_xps_tag_name(&n, "VisualBrush.Visual") {
+            parse_render_node(n, &mut visual, resources)?;
+        }
+    }
+
+    let visual = match visual {
+        RenderNode::Root(children) => children,
+        _ => unreachable!(),
+    };
+
+    Ok(VisualBrush { tile, visual })
 }
 
-fn parse_path_data<'a, 'i: 'a>(_node: roxmltree::Node<'a, 'i>) -> ParseResult<()> {
-    // TODO
+// Parses the properties common to linear and radial gradient brushes
+fn parse_gradient<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>, tag: &str) -> ParseResult<Gradient> {
+    let mut gradient = Gradient {
+        opacity: parse_brush_opacity(node),
+        ..Gradient::default()
+    };
+
+    if let Some(m) = node
+        .attribute("ColorInterpolationMode")
+        .and_then(|s| s.parse::<ColorInterpolationMode>().ok())
+    {
+        gradient.color_interpolation_mode = m;
+    }
+
+    if let Some(m) = node
+        .attribute("SpreadMethod")
+        .and_then(|s| s.parse::<SpreadMethod>().ok())
+    {
+        gradient.spread_method = m;
+    }
+
+    if let Some(m) = node
+        .attribute("MappingMode")
+        .and_then(|s| s.parse::<MappingMode>().ok())
+    {
+        gradient.mapping_mode = m;
+    }
+
+    gradient.transform = node
+        .attribute("Transform")
+        .and_then(|s| parse_matrix("Transform", s));
+
+    let transform_tag = format!("{}.Transform", tag);
+    let stops_tag = format!("{}.GradientStops", tag);
+
+    for n in node.children() {
+        if has_xps_tag_name(&n, &transform_tag) {
+            gradient.transform = parse_matrix_transform(n)?;
+        } else if has_xps_tag_name(&n, &stops_tag) {
+            for n in n.children().filter(|n| has_xps_tag_name(n, "GradientStop")) {
+                let color = n
+                    .attribute("Color")
+                    .and_then(|s| s.parse::<Color>().ok())
+                    .ok_or(ParseError::InvalidBrush)?;
+
+                let offset = n
+                    .attribute("Offset")
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .ok_or(ParseError::InvalidBrush)?;
+
+                gradient.gradient_stops.push(GradientStop { color, offset });
+            }
+        }
+    }
+
+    // Stops are sorted by offset, keeping the document order for
+    // stops with the same offset
+    gradient
+        .gradient_stops
+        .sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(Ordering::Equal));
+
+    Ok(gradient)
+}
+
+// Parses the properties common to image and visual brushes
+fn parse_tile_brush<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    tag: &str,
+) -> ParseResult<TileBrush> {
+    let mut tile = TileBrush {
+        opacity: parse_brush_opacity(node),
+        viewbox: node
+            .attribute("Viewbox")
+            .and_then(|s| s.parse::<Rect>().ok())
+            .ok_or(ParseError::InvalidBrush)?,
+        viewport: node
+            .attribute("Viewport")
+            .and_then(|s| s.parse::<Rect>().ok())
+            .ok_or(ParseError::InvalidBrush)?,
+        ..TileBrush::default()
+    };
+
+    if let Some(m) = node
+        .attribute("ViewboxUnits")
+        .and_then(|s| s.parse::<MappingMode>().ok())
+    {
+        tile.viewbox_units = m;
+    }
+
+    if let Some(m) = node
+        .attribute("ViewportUnits")
+        .and_then(|s| s.parse::<MappingMode>().ok())
+    {
+        tile.viewport_units = m;
+    }
+
+    if let Some(m) = node
+        .attribute("TileMode")
+        .and_then(|s| s.parse::<TileMode>().ok())
+    {
+        tile.tile_mode = m;
+    }
+
+    tile.transform = node
+        .attribute("Transform")
+        .and_then(|s| parse_matrix("Transform", s));
+
+    let transform_tag = format!("{}.Transform", tag);
+
+    for n in node.children() {
+        if has_xps_tag_name(&n, &transform_tag) {
+            tile.transform = parse_matrix_transform(n)?;
+        }
+    }
+
+    Ok(tile)
+}
+
+fn parse_brush_opacity<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> f64 {
+    node.attribute("Opacity")
+        .and_then(|s| s.parse::<f64>().ok())
+        .map_or(1.0, |o| o.clamp(0.0, 1.0))
+}
+
+fn parse_brush_point(s: &str) -> ParseResult<Point> {
+    parse_point(s).map_err(|_| ParseError::InvalidBrush)
+}
+
+// Returns the resources in scope for the children of the node,
+// nested in the parent scope
+fn parse_resources<'a, 'i: 'a, 'p>(
+    node: roxmltree::Node<'a, 'i>,
+    tag: &str,
+    parent: &'p Resources<'p>,
+) -> ParseResult<Resources<'p>> {
+    let mut resources = Resources::new(parent);
+
+    for n in node.children().filter(|n| has_xps_tag_name(n, tag)) {
+        for n in n
+            .children()
+            .filter(|n| has_xps_tag_name(n, "ResourceDictionary"))
+        {
+            // The resources of a dictionary with a source are in a
+            // separate part
+            match n.attribute("Source") {
+                Some(source) => resources.set_remote(parent.load_remote(source)?),
+                None => parse_resource_dictionary(n, &mut resources)?,
+            }
+        }
+    }
+
+    Ok(resources)
+}
+
+fn parse_resource_dictionary<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &mut Resources<'_>,
+) -> ParseResult<()> {
+    let elements = node.children().filter(|n| n.is_element());
+
+    // All the keys are declared upfront, to tell apart the references
+    // to undefined keys from the references to keys declared later
+    for n in elements.clone() {
+        resources.declare(parse_resource_key(n)?)?;
+    }
+
+    for n in elements {
+        let resource = parse_resource(n, resources)?;
+        resources.insert(parse_resource_key(n)?, resource);
+    }
+
     Ok(())
 }
 
+fn parse_resource_key<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<&'a str> {
+    node.attribute((ns::RESOURCE_KEY, "Key"))
+        .or_else(|| node.attribute((ns::OXPS_RESOURCE_KEY, "Key")))
+        .ok_or(ParseError::MissingResourceKey)
+}
+
+fn parse_resource<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    resources: &Resources<'_>,
+) -> ParseResult<Resource> {
+    if let Some(brush) = parse_brush_element(node, resources)? {
+        Ok(Resource::Brush(brush))
+    } else if let Some(visual) = parse_visual(node, resources)? {
+        Ok(Resource::Visual(Box::new(visual)))
+    } else if has_xps_tag_name(&node, "PathGeometry") {
+        parse_path_geometry(node).map(Resource::Geometry)
+    } else if has_xps_tag_name(&node, "MatrixTransform") {
+        node.attribute("Matrix")
+            .and_then(|s| s.parse::<RenderTransform>().ok())
+            .map(Resource::Transform)
+            .ok_or_else(|| ParseError::InvalidResource(node.tag_name().name().to_string()))
+    } else {
+        Err(ParseError::InvalidResource(
+            node.tag_name().name().to_string(),
+        ))
+    }
+}
+
+// Returns the key of a "{StaticResource key}" attribute value
+fn static_resource_key(s: &str) -> Option<&str> {
+    let s = s.trim().strip_prefix('{')?.strip_suffix('}')?;
+
+    let mut args = s.split_whitespace();
+    if args.next()? != "StaticResource" {
+        return None;
+    }
+
+    let key = args.next()?;
+
+    match args.next() {
+        Some(_) => None,
+        None => Some(key),
+    }
+}
+
+// The value of the brush, geometry and transform attributes is either
+// the abbreviated syntax or a reference to a resource
+
+fn parse_brush_attribute<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    name: &str,
+    resources: &Resources<'_>,
+) -> ParseResult<Option<Brush>> {
+    match node.attribute(name) {
+        Some(s) => match static_resource_key(s) {
+            Some(key) => resources.brush(key).map(Some),
+            None => Ok(s.parse::<Brush>().ok()),
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_geometry_attribute<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    name: &str,
+    resources: &Resources<'_>,
+) -> ParseResult<Option<Geometry>> {
+    match node.attribute(name) {
+        Some(s) => match static_resource_key(s) {
+            Some(key) => resources.geometry(key).map(Some),
+            // An invalid geometry only drops the geometry, not the page
+            None => match s.parse::<Geometry>() {
+                Ok(geometry) => Ok(Some(geometry)),
+                Err(e) => {
+                    warn!("Ignoring {} attribute: {}", name, e);
+                    Ok(None)
+                }
+            },
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_transform_attribute<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    name: &str,
+    resources: &Resources<'_>,
+) -> ParseResult<Option<RenderTransform>> {
+    match node.attribute(name) {
+        Some(s) => match static_resource_key(s) {
+            Some(key) => resources.transform(key).map(Some),
+            None => Ok(parse_matrix(name, s)),
+        },
+        None => Ok(None),
+    }
+}
+
+// Like the Data and Clip attributes, an invalid geometry only drops
+// the geometry, not the page
+fn parse_path_data<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> Option<Geometry> {
+    for n in node.children() {
+        if has_xps_tag_name(&n, "PathGeometry") {
+            return match parse_path_geometry(n) {
+                Ok(geometry) => Some(geometry),
+                Err(e) => {
+                    warn!("Ignoring {}: {}", node.tag_name().name(), e);
+                    None
+                }
+            };
+        }
+    }
+
+    None
+}
+
+fn parse_path_geometry<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Geometry> {
+    let mut geometry = match node.attribute("Figures") {
+        Some(s) => s.parse::<Geometry>()?,
+        None => Geometry::default(),
+    };
+
+    if let Some(fill_rule) = node.attribute("FillRule").and_then(parse_fill_rule) {
+        geometry.fill_rule = fill_rule;
+    }
+
+    geometry.transform = node
+        .attribute("Transform")
+        .and_then(|s| parse_matrix("Transform", s));
+
+    for n in node.children() {
+        if has_xps_tag_name(&n, "PathGeometry.Transform") {
+            geometry.transform = parse_matrix_transform(n)?;
+        } else if has_xps_tag_name(&n, "PathFigure") {
+            let figure = parse_path_figure(n)?;
+            geometry.figures.push(figure);
+        }
+    }
+
+    Ok(geometry)
+}
+
+fn parse_path_figure<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<Figure> {
+    let start = node
+        .attribute("StartPoint")
+        .ok_or(ParseError::InvalidPathData)
+        .and_then(parse_point)?;
+
+    let mut figure = Figure::new(start);
+
+    if let Some(b) = node.attribute("IsClosed").and_then(parse_bool) {
+        figure.is_closed = b;
+    }
+
+    if let Some(b) = node.attribute("IsFilled").and_then(parse_bool) {
+        figure.is_filled = b;
+    }
+
+    for n in node.children().filter(|n| n.is_element()) {
+        let is_stroked = n
+            .attribute("IsStroked")
+            .and_then(parse_bool)
+            .unwrap_or(true);
+
+        let kinds = if has_xps_tag_name(&n, "ArcSegment") {
+            vec![parse_arc_segment(n)?]
+        } else if has_xps_tag_name(&n, "PolyBezierSegment") {
+            let points = parse_segment_points(n, 3)?;
+            points
+                .chunks(3)
+                .map(|p| SegmentKind::CubicBezier(p[0], p[1], p[2]))
+                .collect()
+        } else if has_xps_tag_name(&n, "PolyLineSegment") {
+            let points = parse_segment_points(n, 1)?;
+            points.into_iter().map(SegmentKind::Line).collect()
+        } else if has_xps_tag_name(&n, "PolyQuadraticBezierSegment") {
+            let points = parse_segment_points(n, 2)?;
+            points
+                .chunks(2)
+                .map(|p| SegmentKind::QuadraticBezier(p[0], p[1]))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        figure
+            .segments
+            .extend(kinds.into_iter().map(|kind| Segment { kind, is_stroked }));
+    }
+
+    Ok(figure)
+}
+
+fn parse_arc_segment<'a, 'i: 'a>(node: roxmltree::Node<'a, 'i>) -> ParseResult<SegmentKind> {
+    let point = node
+        .attribute("Point")
+        .ok_or(ParseError::InvalidPathData)
+        .and_then(parse_point)?;
+
+    let size = node
+        .attribute("Size")
+        .ok_or(ParseError::InvalidPathData)
+        .and_then(parse_point)?;
+
+    let rotation_angle = node
+        .attribute("RotationAngle")
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or(ParseError::InvalidPathData)?;
+
+    let is_large_arc = node
+        .attribute("IsLargeArc")
+        .and_then(parse_bool)
+        .ok_or(ParseError::InvalidPathData)?;
+
+    let sweep_direction = match node.attribute("SweepDirection") {
+        Some("Clockwise") => SweepDirection::Clockwise,
+        Some("Counterclockwise") => SweepDirection::Counterclockwise,
+        _ => return Err(ParseError::InvalidPathData),
+    };
+
+    Ok(SegmentKind::Arc(Arc {
+        point,
+        size: (size.x, size.y),
+        rotation_angle,
+        is_large_arc,
+        sweep_direction,
+    }))
+}
+
+// Parses the Points attribute of a poly segment, which must contain
+// a multiple of `n` points
+fn parse_segment_points<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    n: usize,
+) -> ParseResult<Vec<Point>> {
+    let points = node
+        .attribute("Points")
+        .ok_or(ParseError::InvalidPathData)?
+        .split_whitespace()
+        .map(parse_point)
+        .collect::<ParseResult<Vec<Point>>>()?;
+
+    if points.is_empty() || points.len() % n != 0 {
+        return Err(ParseError::InvalidPathData);
+    }
+
+    Ok(points)
+}
+
+fn parse_matrix_transform<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+) -> ParseResult<Option<RenderTransform>> {
+    for n in node.children() {
+        if has_xps_tag_name(&n, "MatrixTransform") {
+            let name = node.tag_name().name();
+            return Ok(n.attribute("Matrix").and_then(|s| parse_matrix(name, s)));
+        }
+    }
+
+    Ok(None)
+}
+
+// An invalid matrix is dropped with a warning, like an invalid geometry
+fn parse_matrix(name: &str, s: &str) -> Option<RenderTransform> {
+    let matrix = s.parse::<RenderTransform>().ok();
+
+    if matrix.is_none() {
+        warn!("Ignoring {} with invalid matrix {}", name, s);
+    }
+
+    matrix
+}
+
+fn parse_fill_rule(s: &str) -> Option<FillRule> {
+    match s {
+        "EvenOdd" => Some(FillRule::EvenOdd),
+        "NonZero" => Some(FillRule::NonZero),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoteResourceDictionary;
+
+impl RemoteResourceDictionary {
+    /// Parses a remote resource dictionary part. Its resources can only
+    /// reference the other resources of the same part.
+    pub fn parse(dictionary: &str) -> Result<Resources<'static>> {
+        let doc = roxmltree::Document::parse(dictionary)?;
+
+        let mut resources = Resources::default();
+
+        for node in doc
+            .root()
+            .children()
+            .filter(|n| has_xps_tag_name(n, "ResourceDictionary"))
+        {
+            parse_resource_dictionary(node, &mut resources)?;
+        }
+
+        Ok(resources)
+    }
+}
+
+#[derive(Debug)]
+pub struct FontPart;
+
+impl FontPart {
+    /// Returns the font data of the font part `name`, de-obfuscating
+    /// it if needed according to its content type or its extension,
+    /// see section 9.1.7.3 of the XPS spec.
+    pub fn parse<P: AsRef<std::path::Path>>(
+        name: P,
+        content_type: Option<&str>,
+        mut data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let name = name.as_ref();
+
+        let obfuscated = content_type == Some(content_types::ty::OBFUSCATED_FONT)
+            || name
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("odttf"));
+
+        if !obfuscated {
+            return Ok(data);
+        }
+
+        // The key is the GUID in the file name, in reverse byte order
+        let key = name
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(parse_guid)
+            .ok_or_else(|| ParseError::InvalidFontGuid(name.display().to_string()))?;
+
+        for (i, b) in data.iter_mut().take(32).enumerate() {
+            *b ^= key[15 - i % 16];
+        }
+
+        Ok(data)
+    }
+}
+
+// Parses a GUID such as "{0B0E1A2B-3C4D-5E6F-7081-92A3B4C5D6E7}"
+// into its 16 bytes, in the order they are written
+fn parse_guid(s: &str) -> Option<[u8; 16]> {
+    let s = s.strip_prefix('{').unwrap_or(s);
+    let s = s.strip_suffix('}').unwrap_or(s);
+
+    let groups = s.split('-').map(str::len).collect::<Vec<usize>>();
+    if groups != [8, 4, 4, 4, 12] {
+        return None;
+    }
+
+    let hex = s.replace('-', "");
+    let mut res = [0u8; 16];
+
+    for (i, b) in res.iter_mut().enumerate() {
+        *b = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+
+    Some(res)
+}
+
 #[derive(Debug, Default)]
 pub struct DocumentStructure {
     pub(crate) outline: Option<Outline>,
@@ -545,3 +1273,670 @@ fn has_xps_tag_name(node: &roxmltree::Node<'_, '_>, tag: &str) -> bool {
 fn parse_size(s: &str) -> f64 {
     s.parse().ok().filter(|&f| f >= 0.0).unwrap_or_default()
 }
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_point(s: &str) -> ParseResult<Point> {
+    let mut coords = s.split(',').map(|c| c.trim().parse::<f64>());
+
+    match (coords.next(), coords.next(), coords.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Ok(Point::new(x, y)),
+        _ => Err(ParseError::InvalidPathData),
+    }
+}
+
+#[derive(Debug)]
+pub struct ImagePart;
+
+impl ImagePart {
+    /// Decodes the JPEG, PNG or TIFF data of an image part
+    pub fn parse(data: &[u8]) -> Result<Image> {
+        let image = image::load_from_memory(data)?.to_rgba8();
+
+        // FIXME: the resolution of TIFF images is not read
+        let dpi = png_dpi(data)
+            .or_else(|| jpeg_dpi(data))
+            .unwrap_or((96.0, 96.0));
+
+        let (width, height) = image.dimensions();
+
+        Ok(Image::new(width, height, dpi, image.into_raw()))
+    }
+}
+
+// Returns the resolution from the pHYs chunk of a PNG image
+fn png_dpi(data: &[u8]) -> Option<(f64, f64)> {
+    let mut chunks = data.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+
+    while chunks.len() >= 8 {
+        let len = read_u32(&chunks[0..4]) as usize;
+        let kind = &chunks[4..8];
+        let chunk = chunks.get(8..8 + len)?;
+
+        if kind == b"pHYs" && len == 9 {
+            // Only pixels per meter are meaningful
+            if chunk[8] != 1 {
+                return None;
+            }
+
+            let x = read_u32(&chunk[0..4]);
+            let y = read_u32(&chunk[4..8]);
+
+            return valid_dpi(f64::from(x) * 0.0254, f64::from(y) * 0.0254);
+        } else if kind == b"IDAT" {
+            // pHYs must come before the image data
+            return None;
+        }
+
+        // Skip the data and the CRC
+        chunks = chunks.get(8 + len + 4..)?;
+    }
+
+    None
+}
+
+// Returns the resolution from the JFIF header of a JPEG image
+fn jpeg_dpi(data: &[u8]) -> Option<(f64, f64)> {
+    let app0 = data.strip_prefix(&[0xff, 0xd8, 0xff, 0xe0])?;
+    let jfif = app0.get(2..14)?.strip_prefix(b"JFIF\0")?;
+
+    let x = f64::from(u16::from_be_bytes([jfif[3], jfif[4]]));
+    let y = f64::from(u16::from_be_bytes([jfif[5], jfif[6]]));
+
+    match jfif[2] {
+        1 => valid_dpi(x, y),
+        2 => valid_dpi(x * 2.54, y * 2.54),
+        _ => None,
+    }
+}
+
+fn read_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn valid_dpi(x: f64, y: f64) -> Option<(f64, f64)> {
+    if x > 0.0 && y > 0.0 {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs(attributes: &str) -> Glyphs {
+        let xml = format!(
+            r#"<Glyphs xmlns="{}" OriginX="0" OriginY="0" FontRenderingEmSize="12" {}/>"#,
+            ns::XPS,
+            attributes
+        );
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+
+        parse_glyphs(doc.root_element(), &Resources::default()).unwrap()
+    }
+
+    fn path(xml: &str) -> ParseResult<Path> {
+        let xml = xml.replace("<Path ", &format!(r#"<Path xmlns="{}" "#, ns::XPS));
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+
+        parse_path(doc.root_element(), &Resources::default())
+    }
+
+    fn brush(xml: &str) -> ParseResult<Brush> {
+        let xml = xml.replacen("Brush ", &format!(r#"Brush xmlns="{}" "#, ns::XPS), 1);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+
+        parse_brush_element(doc.root_element(), &Resources::default()).map(Option::unwrap)
+    }
+
+    fn geometry(xml: &str) -> ParseResult<Geometry> {
+        let xml = xml.replacen(
+            "<PathGeometry",
+            &format!(r#"<PathGeometry xmlns="{}""#, ns::XPS),
+            1,
+        );
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+
+        parse_path_geometry(doc.root_element())
+    }
+
+    fn rect(r: Rect) -> (f64, f64, f64, f64) {
+        (r.x, r.y, r.width, r.height)
+    }
+
+    fn clip_start(path: &Path) -> Option<Point> {
+        path.clip.as_ref().map(|c| c.0.figures[0].start)
+    }
+
+    #[test]
+    fn clip_attribute() {
+        let p = path(r#"<Path Data="M 0,0 L 10,10" Clip="M 1,2 L 5,0 L 5,5 Z"/>"#).unwrap();
+        assert_eq!(clip_start(&p), Some(Point::new(1.0, 2.0)));
+    }
+
+    #[test]
+    fn clip_element() {
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10">
+                 <Path.Clip>
+                   <PathGeometry Figures="M 3,4 L 5,0 L 5,5 Z"/>
+                 </Path.Clip>
+               </Path>"#,
+        )
+        .unwrap();
+        assert_eq!(clip_start(&p), Some(Point::new(3.0, 4.0)));
+    }
+
+    #[test]
+    fn invalid_geometry_attribute() {
+        let p = path(r##"<Path Data="M 0,0 L 10" Fill="#FF0000" Clip="M 1,2 L 5,0 L 5,5 Z"/>"##)
+            .unwrap();
+        assert!(p.data.is_none());
+        assert!(p.fill.is_some());
+        assert_eq!(clip_start(&p), Some(Point::new(1.0, 2.0)));
+
+        let p = path(r#"<Path Data="M 0,0 L 10,10" Clip="M 1,2 Q"/>"#).unwrap();
+        assert!(p.data.is_some());
+        assert!(p.clip.is_none());
+    }
+
+    #[test]
+    fn invalid_geometry_element() {
+        let p = path(
+            r##"<Path Fill="#FF0000">
+                 <Path.Data>
+                   <PathGeometry>
+                     <PathFigure StartPoint="0,0">
+                       <PolyLineSegment Points="1,1 2"/>
+                     </PathFigure>
+                   </PathGeometry>
+                 </Path.Data>
+                 <Path.Clip>
+                   <PathGeometry Figures="M 3,4 L 5,0 L 5,5 Z"/>
+                 </Path.Clip>
+               </Path>"##,
+        )
+        .unwrap();
+        assert!(p.data.is_none());
+        assert!(p.fill.is_some());
+        assert_eq!(clip_start(&p), Some(Point::new(3.0, 4.0)));
+
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10">
+                 <Path.Clip>
+                   <PathGeometry Figures="M 3,4 L"/>
+                 </Path.Clip>
+               </Path>"#,
+        )
+        .unwrap();
+        assert!(p.data.is_some());
+        assert!(p.clip.is_none());
+
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10">
+                 <Path.Clip>
+                   <PathGeometry>
+                     <PathFigure>
+                       <ArcSegment Point="1,1" Size="1" RotationAngle="0" IsLargeArc="false"
+                           SweepDirection="Clockwise"/>
+                     </PathFigure>
+                   </PathGeometry>
+                 </Path.Clip>
+               </Path>"#,
+        )
+        .unwrap();
+        assert!(p.clip.is_none());
+    }
+
+    #[test]
+    fn duplicate_clip() {
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10" Clip="M 1,2 L 5,0 L 5,5 Z">
+                 <Path.Clip>
+                   <PathGeometry Figures="M 3,4 L 5,0 L 5,5 Z"/>
+                 </Path.Clip>
+               </Path>"#,
+        );
+        assert!(matches!(p, Err(ParseError::DuplicateProperty(ref name)) if name == "Clip"));
+    }
+
+    fn offset(t: Option<RenderTransform>) -> Option<(f64, f64)> {
+        t.map(|t| (t.x0, t.y0))
+    }
+
+    #[test]
+    fn render_transform() {
+        let p = path(r#"<Path Data="M 0,0 L 10,10" RenderTransform="1,0,0,1,5,6"/>"#).unwrap();
+        assert_eq!(offset(p.render_transform), Some((5.0, 6.0)));
+
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10">
+                 <Path.RenderTransform>
+                   <MatrixTransform Matrix="2,0,0,2,7,8"/>
+                 </Path.RenderTransform>
+               </Path>"#,
+        )
+        .unwrap();
+        assert_eq!(offset(p.render_transform), Some((7.0, 8.0)));
+        assert_eq!(p.render_transform.map(|t| t.xx), Some(2.0));
+    }
+
+    #[test]
+    fn invalid_render_transform() {
+        let p = path(r#"<Path Data="M 0,0 L 10,10" RenderTransform="1,0,0,1,5"/>"#).unwrap();
+        assert!(p.render_transform.is_none());
+        assert!(p.data.is_some());
+
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10">
+                 <Path.RenderTransform>
+                   <MatrixTransform Matrix="1,0,0,x,5,6"/>
+                 </Path.RenderTransform>
+               </Path>"#,
+        )
+        .unwrap();
+        assert!(p.render_transform.is_none());
+        assert!(p.data.is_some());
+    }
+
+    #[test]
+    fn duplicate_render_transform() {
+        let p = path(
+            r#"<Path Data="M 0,0 L 10,10" RenderTransform="1,0,0,1,5,6">
+                 <Path.RenderTransform>
+                   <MatrixTransform Matrix="1,0,0,1,7,8"/>
+                 </Path.RenderTransform>
+               </Path>"#,
+        );
+        assert!(
+            matches!(p, Err(ParseError::DuplicateProperty(ref name)) if name == "RenderTransform")
+        );
+    }
+
+    #[test]
+    fn obfuscated_font() {
+        let name = "Resources/{00112233-4455-6677-8899-AABBCCDDEEFF}.odttf";
+        let data = FontPart::parse(name, None, vec![0; 34]).unwrap();
+
+        // The key is the GUID bytes in reverse order, applied to the
+        // first 32 bytes
+        let key = [
+            0xff, 0xee, 0xdd, 0xcc, 0xbb, 0xaa, 0x99, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22,
+            0x11, 0x00,
+        ];
+        assert_eq!(data[..16], key);
+        assert_eq!(data[16..32], key);
+        assert_eq!(data[32..], [0, 0]);
+
+        // De-obfuscating twice gives back the data
+        let name = "Resources/{00112233-4455-6677-8899-AABBCCDDEEFF}.dat";
+        let content_type = Some(content_types::ty::OBFUSCATED_FONT);
+        assert_eq!(
+            FontPart::parse(name, content_type, data).unwrap(),
+            vec![0; 34]
+        );
+
+        assert_eq!(
+            FontPart::parse("Resources/font.ttf", None, vec![1, 2]).unwrap(),
+            vec![1, 2]
+        );
+        assert!(FontPart::parse("Resources/font.odttf", None, vec![1, 2]).is_err());
+    }
+
+    #[test]
+    fn caret_stops() {
+        let g = glyphs(r#"UnicodeString="office" CaretStops="F9""#);
+        assert_eq!(
+            g.caret_stops(),
+            Some(&[true, true, true, true, true, false, false][..])
+        );
+
+        let g = glyphs(r#"UnicodeString="office" CaretStops="F""#);
+        assert_eq!(g.caret_stops(), None);
+
+        let g = glyphs(r#"UnicodeString="a" CaretStops="Z""#);
+        assert_eq!(g.caret_stops(), None);
+    }
+
+    #[test]
+    fn glyphs_properties() {
+        let g = glyphs(r#"UnicodeString="{}{abc}" BidiLevel="1" DeviceFontName="Arial""#);
+        assert_eq!(g.unicode_string(), Some("{abc}"));
+        assert_eq!(g.bidi_level(), Some(1));
+        assert!(g.is_rtl());
+        assert_eq!(g.device_font_name(), Some("Arial"));
+    }
+
+    #[test]
+    fn solid_color_brush() {
+        match brush(r##"<SolidColorBrush Color="#80FF0000" Opacity="0.5"/>"##).unwrap() {
+            Brush::SolidColor(b) => {
+                assert_eq!(b.opacity, 0.5);
+                assert_eq!(
+                    b.color,
+                    Color::SRgb {
+                        a: 0x80,
+                        r: 0xff,
+                        g: 0,
+                        b: 0
+                    }
+                );
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        // The opacity is clamped, and defaults to 1
+        match brush(r##"<SolidColorBrush Color="#FF0000" Opacity="2"/>"##).unwrap() {
+            Brush::SolidColor(b) => assert_eq!(b.opacity, 1.0),
+            b => panic!("unexpected brush {:?}", b),
+        }
+        match brush(r##"<SolidColorBrush Color="#FF0000" Opacity="x"/>"##).unwrap() {
+            Brush::SolidColor(b) => assert_eq!(b.opacity, 1.0),
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        assert!(matches!(
+            brush(r#"<SolidColorBrush Opacity="0.5"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+    }
+
+    #[test]
+    fn linear_gradient_brush() {
+        let b = brush(
+            r##"<LinearGradientBrush StartPoint="0,0" EndPoint="1,0.5" Opacity="0.25"
+                   MappingMode="RelativeToBoundingBox" SpreadMethod="Reflect"
+                   ColorInterpolationMode="ScRgbLinearInterpolation">
+                 <LinearGradientBrush.GradientStops>
+                   <GradientStop Color="#0000FF" Offset="1"/>
+                   <GradientStop Color="#FF0000" Offset="0"/>
+                   <GradientStop Color="#00FF00" Offset="0"/>
+                 </LinearGradientBrush.GradientStops>
+               </LinearGradientBrush>"##,
+        );
+
+        match b.unwrap() {
+            Brush::LinearGradient(b) => {
+                assert_eq!(b.start_point, Point::new(0.0, 0.0));
+                assert_eq!(b.end_point, Point::new(1.0, 0.5));
+
+                let g = &b.gradient;
+                assert_eq!(g.opacity, 0.25);
+                assert_eq!(g.mapping_mode, MappingMode::RelativeToBoundingBox);
+                assert_eq!(g.spread_method, SpreadMethod::Reflect);
+                assert_eq!(
+                    g.color_interpolation_mode,
+                    ColorInterpolationMode::ScRgbLinearInterpolation
+                );
+
+                // Sorted by offset, in document order for equal offsets
+                let stops = g
+                    .gradient_stops
+                    .iter()
+                    .map(|s| (s.offset, s.color.to_srgb8()))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    stops,
+                    [
+                        (0.0, (255, 0, 0, 255)),
+                        (0.0, (0, 255, 0, 255)),
+                        (1.0, (0, 0, 255, 255))
+                    ]
+                );
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        // Defaults
+        match brush(r#"<LinearGradientBrush StartPoint="0,0" EndPoint="1,1"/>"#).unwrap() {
+            Brush::LinearGradient(b) => {
+                assert_eq!(b.gradient.opacity, 1.0);
+                assert_eq!(b.gradient.mapping_mode, MappingMode::Absolute);
+                assert_eq!(b.gradient.spread_method, SpreadMethod::Pad);
+                assert!(b.gradient.gradient_stops.is_empty());
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        assert!(matches!(
+            brush(r#"<LinearGradientBrush StartPoint="0,0"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+        assert!(matches!(
+            brush(
+                r#"<LinearGradientBrush StartPoint="0,0" EndPoint="1,1">
+                     <LinearGradientBrush.GradientStops>
+                       <GradientStop Offset="1"/>
+                     </LinearGradientBrush.GradientStops>
+                   </LinearGradientBrush>"#
+            ),
+            Err(ParseError::InvalidBrush)
+        ));
+    }
+
+    #[test]
+    fn radial_gradient_brush() {
+        let b = brush(
+            r#"<RadialGradientBrush Center="10,20" GradientOrigin="5,15" RadiusX="30" RadiusY="40"
+                   SpreadMethod="Repeat"/>"#,
+        );
+
+        match b.unwrap() {
+            Brush::RadialGradient(b) => {
+                assert_eq!(b.center, Point::new(10.0, 20.0));
+                assert_eq!(b.gradient_origin, Point::new(5.0, 15.0));
+                assert_eq!((b.radius_x, b.radius_y), (30.0, 40.0));
+                assert_eq!(b.gradient.spread_method, SpreadMethod::Repeat);
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        assert!(matches!(
+            brush(r#"<RadialGradientBrush Center="10,20" GradientOrigin="5,15" RadiusX="30"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+    }
+
+    #[test]
+    fn image_brush() {
+        let b = brush(
+            r#"<ImageBrush ImageSource="/Resources/image.png" Viewbox="0,0,96,48"
+                   Viewport="10,20,100,50" ViewboxUnits="Absolute" ViewportUnits="Absolute"
+                   TileMode="FlipXY" Opacity="0.5"/>"#,
+        );
+
+        match b.unwrap() {
+            Brush::Image(b) => {
+                assert_eq!(b.image_source, "/Resources/image.png");
+                assert!(b.image.is_none());
+                assert_eq!(b.tile.opacity, 0.5);
+                assert_eq!(rect(b.tile.viewbox), (0.0, 0.0, 96.0, 48.0));
+                assert_eq!(rect(b.tile.viewport), (10.0, 20.0, 100.0, 50.0));
+                assert_eq!(b.tile.viewbox_units, MappingMode::Absolute);
+                assert_eq!(b.tile.viewport_units, MappingMode::Absolute);
+                assert_eq!(b.tile.tile_mode, TileMode::FlipXY);
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        let b = brush(
+            r#"<ImageBrush ImageSource="{ColorConvertedBitmap /Resources/image.tif /Resources/profile.icc}"
+                   Viewbox="0,0,1,1" Viewport="0,0,1,1"/>"#,
+        );
+
+        match b.unwrap() {
+            Brush::Image(b) => {
+                assert_eq!(b.image_source, "/Resources/image.tif");
+                assert_eq!(b.tile.tile_mode, TileMode::None);
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+
+        // The viewbox and viewport are required, with a positive size
+        assert!(matches!(
+            brush(r#"<ImageBrush ImageSource="image.png" Viewbox="0,0,1,1"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+        assert!(matches!(
+            brush(r#"<ImageBrush ImageSource="image.png" Viewbox="0,0,1,1" Viewport="0,0,-1,1"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+        assert!(matches!(
+            brush(r#"<ImageBrush ImageSource="image.png" Viewbox="0,0,1" Viewport="0,0,1,1"/>"#),
+            Err(ParseError::InvalidBrush)
+        ));
+    }
+
+    #[test]
+    fn visual_brush() {
+        let b = brush(
+            r##"<VisualBrush Viewbox="0,0,10,10" Viewport="0,0,20,20" TileMode="Tile">
+                 <VisualBrush.Visual>
+                   <Canvas>
+                     <Path Data="M 0,0 L 10,10" Fill="#FF0000"/>
+                   </Canvas>
+                   <Path Data="M 0,10 L 10,0" Stroke="#0000FF"/>
+                 </VisualBrush.Visual>
+               </VisualBrush>"##,
+        );
+
+        match b.unwrap() {
+            Brush::Visual(b) => {
+                assert_eq!(rect(b.tile.viewbox), (0.0, 0.0, 10.0, 10.0));
+                assert_eq!(rect(b.tile.viewport), (0.0, 0.0, 20.0, 20.0));
+                assert_eq!(b.tile.tile_mode, TileMode::Tile);
+                assert!(matches!(
+                    b.visual[..],
+                    [RenderNode::Canvas(ref canvas), RenderNode::Path(_)]
+                        if canvas.children.len() == 1
+                ));
+            }
+            b => panic!("unexpected brush {:?}", b),
+        }
+    }
+
+    #[test]
+    fn path_figure_segments() {
+        let g = geometry(
+            r#"<PathGeometry FillRule="NonZero">
+                 <PathFigure StartPoint="1,2" IsClosed="true" IsFilled="false">
+                   <PolyLineSegment Points="3,4 5,6"/>
+                   <ArcSegment Point="7,8" Size="10,20" RotationAngle="45" IsLargeArc="true"
+                       SweepDirection="Counterclockwise" IsStroked="false"/>
+                   <PolyBezierSegment Points="1,1 2,2 3,3 4,4 5,5 6,6"/>
+                   <PolyQuadraticBezierSegment Points="1,0 2,0"/>
+                 </PathFigure>
+               </PathGeometry>"#,
+        )
+        .unwrap();
+
+        assert_eq!(g.fill_rule, FillRule::NonZero);
+        assert_eq!(g.figures.len(), 1);
+
+        let f = &g.figures[0];
+        assert_eq!(f.start, Point::new(1.0, 2.0));
+        assert!(f.is_closed);
+        assert!(!f.is_filled);
+
+        let segments = &f.segments;
+        assert_eq!(segments.len(), 6);
+        assert!(matches!(segments[0].kind, SegmentKind::Line(p) if p == Point::new(3.0, 4.0)));
+        assert!(matches!(segments[1].kind, SegmentKind::Line(p) if p == Point::new(5.0, 6.0)));
+        match segments[2].kind {
+            SegmentKind::Arc(arc) => {
+                assert_eq!(arc.point, Point::new(7.0, 8.0));
+                assert_eq!(arc.size, (10.0, 20.0));
+                assert_eq!(arc.rotation_angle, 45.0);
+                assert!(arc.is_large_arc);
+                assert_eq!(arc.sweep_direction, SweepDirection::Counterclockwise);
+            }
+            kind => panic!("unexpected segment {:?}", kind),
+        }
+        assert!(matches!(
+            segments[3].kind,
+            SegmentKind::CubicBezier(_, _, p) if p == Point::new(3.0, 3.0)
+        ));
+        assert!(matches!(
+            segments[4].kind,
+            SegmentKind::CubicBezier(p, _, _) if p == Point::new(4.0, 4.0)
+        ));
+        assert!(matches!(
+            segments[5].kind,
+            SegmentKind::QuadraticBezier(p1, p2)
+                if p1 == Point::new(1.0, 0.0) && p2 == Point::new(2.0, 0.0)
+        ));
+
+        let stroked = segments.iter().map(|s| s.is_stroked).collect::<Vec<_>>();
+        assert_eq!(stroked, [true, true, false, true, true, true]);
+    }
+
+    #[test]
+    fn path_figure_defaults() {
+        let g = geometry(
+            r#"<PathGeometry>
+                 <PathFigure StartPoint="0,0">
+                   <PolyLineSegment Points="1,1"/>
+                 </PathFigure>
+               </PathGeometry>"#,
+        )
+        .unwrap();
+
+        assert_eq!(g.fill_rule, FillRule::EvenOdd);
+        assert!(!g.figures[0].is_closed);
+        assert!(g.figures[0].is_filled);
+    }
+
+    #[test]
+    fn path_geometry_figures() {
+        // The figures of the attribute come before the child figures
+        let g = geometry(
+            r#"<PathGeometry Figures="M 0,0 L 1,1 M 2,2 L 3,3 Z">
+                 <PathFigure StartPoint="4,4">
+                   <PolyLineSegment Points="5,5"/>
+                 </PathFigure>
+               </PathGeometry>"#,
+        )
+        .unwrap();
+
+        let starts = g.figures.iter().map(|f| f.start).collect::<Vec<_>>();
+        assert_eq!(
+            starts,
+            [
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 2.0),
+                Point::new(4.0, 4.0)
+            ]
+        );
+        assert!(!g.figures[0].is_closed);
+        assert!(g.figures[1].is_closed);
+        assert!(!g.figures[2].is_closed);
+    }
+
+    #[test]
+    fn invalid_path_figure() {
+        let invalid = [
+            r#"<PathGeometry><PathFigure><PolyLineSegment Points="1,1"/></PathFigure></PathGeometry>"#,
+            r#"<PathGeometry><PathFigure StartPoint="0,0"><PolyLineSegment/></PathFigure></PathGeometry>"#,
+            r#"<PathGeometry><PathFigure StartPoint="0,0"><PolyBezierSegment Points="1,1 2,2"/></PathFigure></PathGeometry>"#,
+            r#"<PathGeometry><PathFigure StartPoint="0,0"><PolyQuadraticBezierSegment Points="1,1"/></PathFigure></PathGeometry>"#,
+            r#"<PathGeometry><PathFigure StartPoint="0,0"><ArcSegment Point="1,1" Size="1,1" RotationAngle="0" IsLargeArc="false"/></PathFigure></PathGeometry>"#,
+            r#"<PathGeometry Figures="M 0,0 X"/>"#,
+        ];
+
+        for xml in invalid.iter() {
+            assert!(
+                matches!(geometry(xml), Err(ParseError::InvalidPathData)),
+                "{}",
+                xml
+            );
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::io::Write;
+
+use log::warn;
+
+use crate::cairo_renderer::{destination_name, quote_attribute, CairoRenderer};
+use crate::error::{RenderError, Result};
+use crate::xps::{Document, Page, XPS};
+
+/// PDF writer, converting documents with cairo. Paths and text are
+/// kept as vectors, with subsets of the fonts embedded, the outlines
+/// of the documents become bookmarks and hyperlinks become link
+/// annotations.
+#[derive(Debug)]
+pub struct PdfWriter<'a> {
+    surface: cairo::PdfSurface,
+    cr: cairo::Context,
+    renderer: CairoRenderer,
+    documents: Vec<(&'a Document, Vec<&'a Page>)>,
+}
+
+impl<'a> PdfWriter<'a> {
+    /// Returns a writer of a PDF document to `stream`
+    pub fn new<W: Write + 'static>(stream: W) -> Result<Self> {
+        // The size is set for each page before drawing it
+        let surface =
+            cairo::PdfSurface::for_stream(1.0, 1.0, stream).map_err(RenderError::Cairo)?;
+        let cr = cairo::Context::new(&surface);
+        let renderer = CairoRenderer::with_font_faces(cr.clone());
+
+        Ok(Self {
+            surface,
+            cr,
+            renderer,
+            documents: Vec::new(),
+        })
+    }
+
+    /// Appends the pages of the document with the given indices.
+    /// Only the links and the outline entries pointing to the pages
+    /// of the PDF document are kept.
+    pub fn add_document(&mut self, document: &'a Document, pages: &[usize]) {
+        let pages = pages
+            .iter()
+            .filter_map(|&i| document.pages().get(i))
+            .collect::<Vec<_>>();
+
+        self.documents.push((document, pages));
+    }
+
+    /// Converts the documents and writes the PDF document to the stream.
+    /// The documents are only converted here, since links can point to
+    /// the pages of any of them.
+    pub fn finish(self) -> Result<()> {
+        // Links can point to the pages and to their link targets
+        for page in self.documents.iter().flat_map(|(_, pages)| pages) {
+            let mut destinations = self.renderer.destinations();
+            destinations.names.insert(page_destination(page));
+            destinations.names.extend(page.links.iter().cloned());
+        }
+
+        for (document, pages) in self.documents.iter() {
+            self.add_pages(pages)?;
+            self.add_outline(document, pages)?;
+        }
+
+        self.surface.finish_output_stream().map_err(|e| e.error)?;
+
+        match self.cr.status() {
+            cairo::Status::Success => Ok(()),
+            status => Err(RenderError::Cairo(status).into()),
+        }
+    }
+
+    fn add_pages(&self, pages: &[&Page]) -> Result<()> {
+        for page in pages.iter() {
+            // The page is loaded first to get its own size, a page that
+            // fails to load is reported when it is rendered
+            page.load().ok();
+
+            let (width, height) = page.size();
+            self.surface
+                .set_size(width, height)
+                .map_err(RenderError::Cairo)?;
+
+            self.add_destination(&page_destination(page));
+
+            self.renderer.destinations().pending = page.links.iter().cloned().collect();
+
+            if let Err(e) = page.render(&self.renderer) {
+                warn!("Failed to render page {}: {}", page.path.display(), e);
+            }
+
+            // The link targets that were not drawn point to the page
+            let pending = std::mem::take(&mut self.renderer.destinations().pending);
+            for name in pending {
+                self.add_destination(&name);
+            }
+
+            self.cr.show_page();
+        }
+
+        match self.cr.status() {
+            cairo::Status::Success => Ok(()),
+            status => Err(RenderError::Cairo(status).into()),
+        }
+    }
+
+    // Adds the outline entries of the document as bookmarks, nested
+    // according to their level
+    fn add_outline(&self, document: &Document, pages: &[&Page]) -> Result<()> {
+        if let (Some(outline), Some(first_page)) = (document.outline(), pages.first()) {
+            // The ids of the last entry of each level above the current one
+            let mut parents: Vec<(u32, i32)> = Vec::new();
+
+            for entry in outline.entries() {
+                let level = entry
+                    .level
+                    .as_ref()
+                    .and_then(|l| l.parse::<u32>().ok())
+                    .unwrap_or(1);
+
+                while parents.last().is_some_and(|&(l, _)| l >= level) {
+                    parents.pop();
+                }
+
+                let parent = parents
+                    .last()
+                    .map_or(cairo::PDF_OUTLINE_ROOT, |&(_, id)| id);
+
+                // Entries without a valid target point to the first page
+                let dest = entry
+                    .target
+                    .as_ref()
+                    .map(|t| destination_name(t))
+                    .filter(|d| self.renderer.destinations().names.contains(d))
+                    .unwrap_or_else(|| page_destination(first_page));
+
+                let description = entry.description.as_deref().unwrap_or_default();
+                let attributes = format!("dest={}", quote_attribute(&dest));
+
+                let id = self
+                    .surface
+                    .add_outline(parent, description, &attributes, cairo::PdfOutline::empty())
+                    .map_err(RenderError::Cairo)?;
+
+                parents.push((level, id));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Adds a named destination at the top left corner of the page
+    fn add_destination(&self, name: &str) {
+        let attributes = format!("name={} x=0 y=0", quote_attribute(name));
+        self.cr.tag_begin(cairo::CAIRO_TAG_DEST, &attributes);
+        self.cr.tag_end(cairo::CAIRO_TAG_DEST);
+    }
+}
+
+impl XPS {
+    /// Converts all the documents to PDF, see `PdfWriter`
+    pub fn write_pdf<W: Write + 'static>(&self, stream: W) -> Result<()> {
+        let mut writer = PdfWriter::new(stream)?;
+
+        for document in self.documents() {
+            let pages = (0..document.pages().len()).collect::<Vec<_>>();
+            writer.add_document(document, &pages);
+        }
+
+        writer.finish()
+    }
+}
+
+fn page_destination(page: &Page) -> String {
+    destination_name(&page.path.display().to_string())
+}
@@ -1,6 +1,11 @@
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use crate::brush::Brush;
 use crate::error::RenderResult;
+use crate::font::Font;
+use crate::geometry::{Geometry, Point};
 
 /// Renderer trait
 pub trait Renderer {
@@ -36,7 +41,7 @@ pub trait Renderer {
     fn render_path(&self, path: &Path) -> RenderResult<()>;
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum RenderNode {
     Root(Vec<RenderNode>),
     Canvas(Canvas),
@@ -68,6 +73,27 @@ pub(crate) struct Rect {
     pub height: f64,
 }
 
+impl FromStr for Rect {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s
+            .split(',')
+            .map(|c| c.trim().parse::<f64>().map_err(|_| ()))
+            .collect::<Result<Vec<f64>, ()>>()?;
+
+        match v[..] {
+            [x, y, width, height] if width >= 0.0 && height >= 0.0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default)]
 pub(crate) struct ContentBox(Rect);
 
@@ -112,123 +138,236 @@ impl FromStr for RenderTransform {
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct Clip {}
+impl RenderTransform {
+    /// Returns the transform that leaves the points unchanged
+    pub fn identity() -> Self {
+        Self {
+            xx: 1.0,
+            yx: 0.0,
+            xy: 0.0,
+            yy: 1.0,
+            x0: 0.0,
+            y0: 0.0,
+        }
+    }
+
+    /// Returns the transform applying `self` and then `other`
+    pub fn multiply(&self, other: &RenderTransform) -> RenderTransform {
+        RenderTransform {
+            xx: self.xx * other.xx + self.yx * other.xy,
+            yx: self.xx * other.yx + self.yx * other.yy,
+            xy: self.xy * other.xx + self.yy * other.xy,
+            yy: self.xy * other.yx + self.yy * other.yy,
+            x0: self.x0 * other.xx + self.y0 * other.xy + other.x0,
+            y0: self.x0 * other.yx + self.y0 * other.yy + other.y0,
+        }
+    }
+
+    /// Returns the point transformed by `self`
+    pub fn transform_point(&self, p: Point) -> Point {
+        Point::new(
+            self.xx * p.x + self.xy * p.y + self.x0,
+            self.yx * p.x + self.yy * p.y + self.y0,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Clip(pub Geometry);
 
 impl FromStr for Clip {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Geometry>().map(Self).map_err(|_| ())
     }
 }
 
 #[derive(Copy, Clone, Debug, Default)]
-pub(crate) struct Opacity(f64);
+pub(crate) struct Opacity(pub f64);
 
 impl FromStr for Opacity {
     type Err = (); // FIXME
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse::<f64>().map(Self).map_err(|_| ())
+        s.parse::<f64>()
+            .map(|o| Self(o.clamp(0.0, 1.0)))
+            .map_err(|_| ())
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct OpacityMask {}
+#[derive(Clone, Debug)]
+pub(crate) struct OpacityMask(pub Brush);
 
 impl FromStr for OpacityMask {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Brush>().map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct Fill {}
+#[derive(Clone, Debug)]
+pub(crate) struct Fill(pub Brush);
 
 impl FromStr for Fill {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Brush>().map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct Stroke {}
+#[derive(Clone, Debug)]
+pub(crate) struct Stroke(pub Brush);
 
 impl FromStr for Stroke {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Brush>().map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeDashArray {}
+/// Dash and gap lengths, in multiples of the stroke thickness
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StrokeDashArray(pub Vec<f64>);
 
 impl FromStr for StrokeDashArray {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(|d| d.parse::<f64>().ok().filter(|&d| d >= 0.0).ok_or(()))
+            .collect::<Result<Vec<f64>, ()>>()
+            .map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeDashOffset {}
+/// Dash offset, in multiples of the stroke thickness
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct StrokeDashOffset(pub f64);
 
 impl FromStr for StrokeDashOffset {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>().map(Self).map_err(|_| ())
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeEndLineCap {}
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum LineCap {
+    #[default]
+    Flat,
+    Round,
+    Square,
+    Triangle,
+}
+
+impl FromStr for LineCap {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Flat" => Ok(LineCap::Flat),
+            "Round" => Ok(LineCap::Round),
+            "Square" => Ok(LineCap::Square),
+            "Triangle" => Ok(LineCap::Triangle),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct StrokeEndLineCap(pub LineCap);
 
 impl FromStr for StrokeEndLineCap {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<LineCap>().map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeStartLineCap {}
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct StrokeStartLineCap(pub LineCap);
 
 impl FromStr for StrokeStartLineCap {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<LineCap>().map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeLineJoin {}
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum LineJoin {
+    #[default]
+    Miter,
+    Bevel,
+    Round,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct StrokeLineJoin(pub LineJoin);
 
 impl FromStr for StrokeLineJoin {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Miter" => Ok(Self(LineJoin::Miter)),
+            "Bevel" => Ok(Self(LineJoin::Bevel)),
+            "Round" => Ok(Self(LineJoin::Round)),
+            _ => Err(()),
+        }
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeMiterLimit {}
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StrokeMiterLimit(pub f64);
 
-#[derive(Debug, Default)]
-pub(crate) struct StrokeThickness {}
+impl Default for StrokeMiterLimit {
+    fn default() -> Self {
+        Self(10.0)
+    }
+}
+
+impl FromStr for StrokeMiterLimit {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .ok()
+            .filter(|&l| l >= 1.0)
+            .map(Self)
+            .ok_or(())
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StrokeThickness(pub f64);
+
+impl Default for StrokeThickness {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl FromStr for StrokeThickness {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .ok()
+            .filter(|&t| t >= 0.0)
+            .map(Self)
+            .ok_or(())
+    }
+}
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct IsSideways(bool);
 
 impl FromStr for IsSideways {
@@ -239,40 +378,195 @@ impl FromStr for IsSideways {
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct Indices(bool);
+/// An entry of the `Indices` attribute. Advance and offsets are
+/// expressed in hundredths of the font em size. The cluster, when
+/// present, is the number of UTF-16 code units of the UnicodeString
+/// and the number of glyphs that map to each other, starting with
+/// this glyph.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct GlyphIndex {
+    pub cluster: Option<(usize, usize)>,
+    pub index: Option<u16>,
+    pub advance: Option<f64>,
+    pub u_offset: f64,
+    pub v_offset: f64,
+}
+
+impl FromStr for GlyphIndex {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::default();
+
+        let s = s.trim_start();
+
+        let s = match s.strip_prefix('(') {
+            Some(s) => {
+                let end = s.find(')').ok_or(())?;
+                let mut counts = s[..end].split(':').map(|c| c.trim().parse::<usize>());
+
+                let code_units = counts.next().ok_or(())?.map_err(|_| ())?;
+                let glyphs = match counts.next() {
+                    Some(g) => g.map_err(|_| ())?,
+                    None => 1,
+                };
+
+                if counts.next().is_some() || glyphs == 0 {
+                    return Err(());
+                }
+
+                res.cluster = Some((code_units, glyphs));
+
+                &s[end + 1..]
+            }
+            None => s,
+        };
+
+        let mut fields = s.split(',').map(str::trim);
+
+        if let Some(f) = fields.next().filter(|f| !f.is_empty()) {
+            res.index = Some(f.parse::<u16>().map_err(|_| ())?);
+        }
+
+        if let Some(f) = fields.next().filter(|f| !f.is_empty()) {
+            res.advance = Some(f.parse::<f64>().map_err(|_| ())?);
+        }
+
+        if let Some(f) = fields.next().filter(|f| !f.is_empty()) {
+            res.u_offset = f.parse::<f64>().map_err(|_| ())?;
+        }
+
+        if let Some(f) = fields.next().filter(|f| !f.is_empty()) {
+            res.v_offset = f.parse::<f64>().map_err(|_| ())?;
+        }
+
+        if fields.next().is_some() {
+            return Err(());
+        }
+
+        Ok(res)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Indices(pub Vec<GlyphIndex>);
 
 impl FromStr for Indices {
     type Err = (); // FIXME
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse::<bool>().map(Self).map_err(|_| ())
+        s.split(';')
+            .map(|e| e.parse::<GlyphIndex>())
+            .collect::<Result<Vec<GlyphIndex>, ()>>()
+            .map(Self)
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct UnicodeString(bool);
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UnicodeString(pub String);
 
 impl FromStr for UnicodeString {
     type Err = (); // FIXME
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse::<bool>().map(Self).map_err(|_| ())
+        // A leading "{}" escapes strings that start with a brace
+        let s = s.strip_prefix("{}").unwrap_or(s);
+
+        Ok(Self(String::from(s)))
+    }
+}
+
+/// The bidirectional nesting level of a glyph run: odd levels
+/// are right to left
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct BidiLevel(pub u8);
+
+impl BidiLevel {
+    pub fn is_rtl(self) -> bool {
+        self.0 % 2 == 1
+    }
+}
+
+impl FromStr for BidiLevel {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u8>()
+            .ok()
+            .filter(|&l| l <= 61)
+            .map(Self)
+            .ok_or(())
+    }
+}
+
+/// Whether a caret stop is allowed before each UTF-16 code unit of
+/// the UnicodeString, plus one final stop after the last code unit
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CaretStops(pub Vec<bool>);
+
+impl FromStr for CaretStops {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Each hex digit holds four stops, most significant bit first
+        let mut stops = Vec::with_capacity(s.len() * 4);
+
+        for c in s.trim().chars() {
+            let d = c.to_digit(16).ok_or(())?;
+            stops.extend((0..4).rev().map(|bit| d & (1 << bit) != 0));
+        }
+
+        Ok(Self(stops))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeviceFontName(pub String);
+
+impl FromStr for DeviceFontName {
+    type Err = (); // FIXME
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(String::from(s)))
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct StyleSimulations(bool);
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum StyleSimulations {
+    None,
+    Italic,
+    Bold,
+    BoldItalic,
+}
+
+impl StyleSimulations {
+    pub fn is_bold(self) -> bool {
+        matches!(self, StyleSimulations::Bold | StyleSimulations::BoldItalic)
+    }
+
+    pub fn is_italic(self) -> bool {
+        matches!(
+            self,
+            StyleSimulations::Italic | StyleSimulations::BoldItalic
+        )
+    }
+}
 
 impl FromStr for StyleSimulations {
     type Err = (); // FIXME
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse::<bool>().map(Self).map_err(|_| ())
+        match s {
+            "None" => Ok(StyleSimulations::None),
+            "ItalicSimulation" => Ok(StyleSimulations::Italic),
+            "BoldSimulation" => Ok(StyleSimulations::Bold),
+            "BoldItalicSimulation" => Ok(StyleSimulations::BoldItalic),
+            _ => Err(()),
+        }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct EdgeMode {}
 
 impl FromStr for EdgeMode {
@@ -283,27 +577,20 @@ impl FromStr for EdgeMode {
     }
 }
 
-#[derive(Debug, Default)]
-pub(crate) struct NavigateUri {}
+/// The hyperlink of an element. Once the page is loaded, URIs that
+/// point inside the package are resolved against the page.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NavigateUri(pub String);
 
 impl FromStr for NavigateUri {
     type Err = (); // FIXME
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {})
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(String::from(s)))
     }
 }
 
-#[derive(Debug)]
-pub(crate) enum Brush {
-    Image,
-    LinearGradient,
-    RadialGradient,
-    SolidColor,
-    Visual,
-}
-
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Canvas {
     // common properties
     pub(crate) name: Option<String>,
@@ -321,7 +608,7 @@ pub struct Canvas {
     pub(crate) children: Vec<RenderNode>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Glyphs {
     // common properties
     pub(crate) name: Option<String>,
@@ -332,7 +619,13 @@ pub struct Glyphs {
     pub(crate) font_uri: String, // FIXME: use a type for URI
     pub(crate) font_rendering_em_size: f64,
 
+    // font loaded from the package
+    pub(crate) font: Option<Arc<Font>>,
+
     // properties
+    pub(crate) bidi_level: Option<BidiLevel>,
+    pub(crate) caret_stops: Option<CaretStops>,
+    pub(crate) device_font_name: Option<DeviceFontName>,
     pub(crate) fill: Option<Fill>,
     pub(crate) render_transform: Option<RenderTransform>,
     pub(crate) clip: Option<Clip>,
@@ -346,7 +639,134 @@ pub struct Glyphs {
     pub(crate) navigate_uri: Option<NavigateUri>,
 }
 
-#[derive(Debug, Default)]
+/// A glyph of a `Glyphs` element together with the range (in bytes)
+/// of the UnicodeString of the cluster it belongs to
+#[derive(Clone, Debug)]
+pub struct ClusterGlyph {
+    pub(crate) entry: GlyphIndex,
+    pub(crate) text: Range<usize>,
+    pub(crate) cluster_start: bool,
+    // The character, when it maps to exactly this glyph
+    pub(crate) character: Option<char>,
+}
+
+impl ClusterGlyph {
+    /// Returns the index of the glyph in the font, when given in the
+    /// Indices
+    pub fn index(&self) -> Option<u16> {
+        self.entry.index
+    }
+
+    /// Returns the range (in bytes) of the UnicodeString of the
+    /// cluster of the glyph
+    pub fn text(&self) -> Range<usize> {
+        self.text.clone()
+    }
+
+    /// Returns whether the glyph is the first one of its cluster
+    pub fn is_cluster_start(&self) -> bool {
+        self.cluster_start
+    }
+
+    /// Returns the character, when it maps to exactly this glyph
+    pub fn character(&self) -> Option<char> {
+        self.character
+    }
+}
+
+impl Glyphs {
+    /// Returns the text of the run, in logical order
+    pub fn unicode_string(&self) -> Option<&str> {
+        self.unicode_string.as_ref().map(|s| &s.0[..])
+    }
+
+    /// Returns the bidirectional nesting level of the run: odd levels
+    /// are right to left
+    pub fn bidi_level(&self) -> Option<u8> {
+        self.bidi_level.map(|l| l.0)
+    }
+
+    /// Returns whether a caret stop is allowed before each UTF-16 code
+    /// unit of the UnicodeString, plus the stop after the last one
+    pub fn caret_stops(&self) -> Option<&[bool]> {
+        self.caret_stops.as_ref().map(|s| &s.0[..])
+    }
+
+    /// Returns the name of the device font the run can be drawn with
+    pub fn device_font_name(&self) -> Option<&str> {
+        self.device_font_name.as_ref().map(|n| &n.0[..])
+    }
+
+    /// Returns whether the run is laid out right to left
+    pub(crate) fn is_rtl(&self) -> bool {
+        self.bidi_level.is_some_and(BidiLevel::is_rtl)
+    }
+
+    /// Returns the glyphs of the run, mapping each of them to the
+    /// characters of the UnicodeString according to the clusters
+    /// specified in the Indices, see section 5.1.6 of the XPS spec
+    pub fn clusters(&self) -> Vec<ClusterGlyph> {
+        let text = self.unicode_string.as_ref().map_or("", |s| &s.0[..]);
+        let entries = self.indices.as_ref().map_or(&[][..], |i| &i.0[..]);
+
+        // Byte offset of each UTF-16 code unit
+        let mut offsets = Vec::new();
+        for (b, c) in text.char_indices() {
+            offsets.extend(std::iter::repeat(b).take(c.len_utf16()));
+        }
+        let units = offsets.len();
+        let byte_offset = |u: usize| offsets.get(u).copied().unwrap_or(text.len());
+
+        let mut res = Vec::new();
+        let mut unit = 0;
+        let mut i = 0;
+
+        while i < entries.len() || unit < units {
+            let entry = entries.get(i).copied().unwrap_or_default();
+
+            let (code_units, glyphs) = match entry.cluster {
+                Some(cluster) => cluster,
+                // Characters outside the BMP are not split by the
+                // default one to one mapping
+                None => {
+                    let c = text[byte_offset(unit)..].chars().next();
+                    (c.map_or(1, char::len_utf16), 1)
+                }
+            };
+
+            let start = byte_offset(unit);
+            let end = byte_offset(unit + code_units);
+
+            let mut chars = text[start..end].chars();
+            let character = match (chars.next(), chars.next()) {
+                (Some(c), None) if glyphs == 1 => Some(c),
+                _ => None,
+            };
+
+            for k in 0..glyphs {
+                let entry = if k == 0 {
+                    entry
+                } else {
+                    entries.get(i + k).copied().unwrap_or_default()
+                };
+
+                res.push(ClusterGlyph {
+                    entry,
+                    text: start..end,
+                    cluster_start: k == 0,
+                    character,
+                });
+            }
+
+            i += glyphs;
+            unit += code_units;
+        }
+
+        res
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Path {
     // common properties
     pub(crate) name: Option<String>,
@@ -366,8 +786,149 @@ pub struct Path {
     pub(crate) stroke_line_join: Option<StrokeLineJoin>,
     pub(crate) stroke_miter_limit: Option<StrokeMiterLimit>,
     pub(crate) stroke_thickness: Option<StrokeThickness>,
-    pub(crate) navigate_uri: Option<String>,
+    pub(crate) navigate_uri: Option<NavigateUri>,
 
     // content
-    pub(crate) data: Option<String>,
+    pub(crate) data: Option<Geometry>,
+}
+
+impl Path {
+    /// Returns the line cap of the stroke. Renderers draw the same
+    /// cap at both ends of the lines, and triangle caps as round caps.
+    pub(crate) fn line_cap(&self) -> LineCap {
+        let start = self.stroke_start_line_cap.map(|c| c.0).unwrap_or_default();
+
+        if start == LineCap::Triangle {
+            return LineCap::Round;
+        }
+
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs(text: &str, indices: &str) -> Glyphs {
+        Glyphs {
+            unicode_string: Some(UnicodeString(String::from(text))),
+            indices: Some(indices.parse::<Indices>().unwrap()),
+            ..Glyphs::default()
+        }
+    }
+
+    // The index, text range and cluster start of each glyph
+    fn clusters(glyphs: &Glyphs) -> Vec<(Option<u16>, Range<usize>, bool)> {
+        glyphs
+            .clusters()
+            .iter()
+            .map(|g| (g.index(), g.text(), g.is_cluster_start()))
+            .collect()
+    }
+
+    #[test]
+    fn line_cap() {
+        let path = |start: LineCap, end: LineCap| Path {
+            stroke_start_line_cap: Some(StrokeStartLineCap(start)),
+            stroke_end_line_cap: Some(StrokeEndLineCap(end)),
+            ..Path::default()
+        };
+
+        assert_eq!(Path::default().line_cap(), LineCap::Flat);
+        assert_eq!(
+            path(LineCap::Square, LineCap::Flat).line_cap(),
+            LineCap::Square
+        );
+        assert_eq!(
+            path(LineCap::Triangle, LineCap::Triangle).line_cap(),
+            LineCap::Round
+        );
+    }
+
+    #[test]
+    fn glyph_index() {
+        let g = "(3:1)2000,50.5,1,-2".parse::<GlyphIndex>().unwrap();
+        assert_eq!(g.cluster, Some((3, 1)));
+        assert_eq!(g.index, Some(2000));
+        assert_eq!(g.advance, Some(50.5));
+        assert_eq!((g.u_offset, g.v_offset), (1.0, -2.0));
+
+        let g = "(2)".parse::<GlyphIndex>().unwrap();
+        assert_eq!(g.cluster, Some((2, 1)));
+        assert_eq!(g.index, None);
+
+        let g = ",,10".parse::<GlyphIndex>().unwrap();
+        assert_eq!((g.index, g.advance, g.u_offset), (None, None, 10.0));
+
+        for s in &["(1:0)", "(1:2:3)", "(a)", "(1", "1,2,3,4,5", "x"] {
+            assert!(s.parse::<GlyphIndex>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn one_to_one() {
+        let g = glyphs("ab", "");
+        assert_eq!(clusters(&g), vec![(None, 0..1, true), (None, 1..2, true)]);
+        assert_eq!(g.clusters()[0].character(), Some('a'));
+    }
+
+    #[test]
+    fn many_characters_to_one_glyph() {
+        // The "ffi" ligature
+        let g = glyphs("office", ";(3:1)2000");
+        assert_eq!(
+            clusters(&g),
+            vec![
+                (None, 0..1, true),
+                (Some(2000), 1..4, true),
+                (None, 4..5, true),
+                (None, 5..6, true),
+            ]
+        );
+        assert_eq!(g.clusters()[1].character(), None);
+    }
+
+    #[test]
+    fn one_character_to_many_glyphs() {
+        // A base glyph and a combining accent
+        let g = glyphs("\u{e9}t", "(1:2)72;300;");
+        assert_eq!(
+            clusters(&g),
+            vec![
+                (Some(72), 0..2, true),
+                (Some(300), 0..2, false),
+                (None, 2..3, true),
+            ]
+        );
+        assert_eq!(g.clusters()[0].character(), None);
+    }
+
+    #[test]
+    fn many_characters_to_many_glyphs() {
+        let g = glyphs("abc", "(2:3)10;11;12;13");
+        assert_eq!(
+            clusters(&g),
+            vec![
+                (Some(10), 0..2, true),
+                (Some(11), 0..2, false),
+                (Some(12), 0..2, false),
+                (Some(13), 2..3, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        // The default mapping keeps the two code units together
+        let g = glyphs("\u{1f600}a", "");
+        assert_eq!(clusters(&g), vec![(None, 0..4, true), (None, 4..5, true)]);
+        assert_eq!(g.clusters()[0].character(), Some('\u{1f600}'));
+
+        let g = glyphs("\u{1f600}a", "(2:1)5;6");
+        assert_eq!(
+            clusters(&g),
+            vec![(Some(5), 0..4, true), (Some(6), 4..5, true)]
+        );
+    }
 }
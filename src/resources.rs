@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::brush::Brush;
+use crate::error::{ParseError, ParseResult};
+use crate::geometry::Geometry;
+use crate::renderer::{RenderNode, RenderTransform};
+
+/// A resource that elements can reference with `{StaticResource key}`
+#[derive(Clone, Debug)]
+pub(crate) enum Resource {
+    Brush(Brush),
+    Geometry(Geometry),
+    Transform(RenderTransform),
+    Visual(Box<RenderNode>),
+}
+
+/// Loads the remote resource dictionary part with the given URI
+pub(crate) type DictionaryLoader<'p> =
+    dyn Fn(&str) -> ParseResult<Arc<Resources<'static>>> + Sync + 'p;
+
+/// The resources in scope while parsing an element: the resource
+/// dictionary of the closest Canvas, chained to the dictionaries of
+/// the outer Canvas elements and of the FixedPage.
+#[derive(Default)]
+pub(crate) struct Resources<'p> {
+    parent: Option<&'p Resources<'p>>,
+    loader: Option<&'p DictionaryLoader<'p>>,
+    remote: Option<Arc<Resources<'static>>>,
+    declared: HashSet<String>,
+    entries: HashMap<String, Resource>,
+}
+
+impl<'p> Resources<'p> {
+    /// Returns an empty scope nested in `parent`
+    pub fn new(parent: &'p Resources<'p>) -> Self {
+        Self {
+            parent: Some(parent),
+            ..Self::default()
+        }
+    }
+
+    /// Returns an empty root scope, using `loader` to load the remote
+    /// dictionaries of the nested scopes
+    pub fn with_loader(loader: &'p DictionaryLoader<'p>) -> Self {
+        Self {
+            loader: Some(loader),
+            ..Self::default()
+        }
+    }
+
+    /// Loads the remote dictionary with the given URI, with the loader
+    /// of the root scope
+    pub fn load_remote(&self, uri: &str) -> ParseResult<Arc<Resources<'static>>> {
+        match (self.loader, self.parent) {
+            (Some(loader), _) => loader(uri),
+            (None, Some(parent)) => parent.load_remote(uri),
+            (None, None) => Err(ParseError::InvalidRemoteResourceDictionary(
+                uri.to_string(),
+                String::from("remote dictionaries cannot reference other ones"),
+            )),
+        }
+    }
+
+    /// Sets the remote dictionary that provides the resources of
+    /// this scope
+    pub fn set_remote(&mut self, remote: Arc<Resources<'static>>) {
+        self.remote = Some(remote);
+    }
+
+    /// Declares a key of the dictionary, before its resource is parsed.
+    /// The keys of a dictionary must be unique.
+    pub fn declare(&mut self, key: &str) -> ParseResult<()> {
+        if !self.declared.insert(key.to_string()) {
+            return Err(ParseError::DuplicateResourceKey(key.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the resources of the dictionary of this scope
+    pub fn resources_mut(&mut self) -> impl Iterator<Item = &mut Resource> {
+        self.entries.values_mut()
+    }
+
+    pub fn insert(&mut self, key: &str, resource: Resource) {
+        self.entries.insert(key.to_string(), resource);
+    }
+
+    /// Returns the resource for the given key. Resources can only
+    /// reference the resources declared before them.
+    pub fn get(&self, key: &str) -> ParseResult<&Resource> {
+        if let Some(resource) = self.entries.get(key) {
+            return Ok(resource);
+        }
+
+        if self.declared.contains(key) {
+            return Err(ParseError::ResourceUsedBeforeDeclaration(key.to_string()));
+        }
+
+        if let Some(resource) = self.remote.as_ref().and_then(|r| r.entries.get(key)) {
+            return Ok(resource);
+        }
+
+        match self.parent {
+            Some(parent) => parent.get(key),
+            None => Err(ParseError::UndefinedResource(key.to_string())),
+        }
+    }
+
+    pub fn brush(&self, key: &str) -> ParseResult<Brush> {
+        match self.get(key)? {
+            Resource::Brush(b) => Ok(b.clone()),
+            _ => Err(ParseError::InvalidResource(key.to_string())),
+        }
+    }
+
+    pub fn geometry(&self, key: &str) -> ParseResult<Geometry> {
+        match self.get(key)? {
+            Resource::Geometry(g) => Ok(g.clone()),
+            _ => Err(ParseError::InvalidResource(key.to_string())),
+        }
+    }
+
+    pub fn transform(&self, key: &str) -> ParseResult<RenderTransform> {
+        match self.get(key)? {
+            Resource::Transform(t) => Ok(*t),
+            _ => Err(ParseError::InvalidResource(key.to_string())),
+        }
+    }
+
+    pub fn visual(&self, key: &str) -> ParseResult<RenderNode> {
+        match self.get(key)? {
+            Resource::Visual(v) => Ok(v.as_ref().clone()),
+            _ => Err(ParseError::InvalidResource(key.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Debug for Resources<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resources")
+            .field("parent", &self.parent)
+            .field("remote", &self.remote)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(x0: f64) -> Resource {
+        Resource::Transform(RenderTransform {
+            x0,
+            ..RenderTransform::identity()
+        })
+    }
+
+    fn x0(resources: &Resources<'_>, key: &str) -> ParseResult<f64> {
+        resources.transform(key).map(|t| t.x0)
+    }
+
+    #[test]
+    fn lookup() {
+        let mut page = Resources::default();
+        page.declare("a").unwrap();
+        page.declare("b").unwrap();
+        page.insert("a", transform(1.0));
+        page.insert("b", transform(2.0));
+
+        // Inner scopes shadow the outer ones
+        let mut canvas = Resources::new(&page);
+        canvas.declare("b").unwrap();
+        canvas.insert("b", transform(3.0));
+
+        assert_eq!(x0(&canvas, "a").unwrap(), 1.0);
+        assert_eq!(x0(&canvas, "b").unwrap(), 3.0);
+        assert_eq!(x0(&page, "b").unwrap(), 2.0);
+
+        assert!(matches!(
+            x0(&canvas, "c"),
+            Err(ParseError::UndefinedResource(ref k)) if k == "c"
+        ));
+        assert!(matches!(
+            canvas.brush("a"),
+            Err(ParseError::InvalidResource(ref k)) if k == "a"
+        ));
+    }
+
+    #[test]
+    fn remote_lookup() {
+        let mut remote = Resources::default();
+        remote.declare("a").unwrap();
+        remote.insert("a", transform(1.0));
+
+        let page = Resources::default();
+        let mut canvas = Resources::new(&page);
+        canvas.set_remote(Arc::new(remote));
+
+        assert_eq!(x0(&canvas, "a").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn declared_before_use() {
+        let mut page = Resources::default();
+        page.declare("a").unwrap();
+        page.insert("a", transform(1.0));
+
+        // While parsing the resources of a dictionary, its keys are
+        // declared but only the previous ones are defined, and they
+        // shadow the keys of the outer scopes
+        let mut canvas = Resources::new(&page);
+        canvas.declare("b").unwrap();
+        canvas.declare("a").unwrap();
+
+        assert!(matches!(
+            x0(&canvas, "a"),
+            Err(ParseError::ResourceUsedBeforeDeclaration(ref k)) if k == "a"
+        ));
+
+        canvas.insert("b", transform(2.0));
+        assert_eq!(x0(&canvas, "b").unwrap(), 2.0);
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let mut resources = Resources::default();
+        resources.declare("a").unwrap();
+
+        assert!(matches!(
+            resources.declare("a"),
+            Err(ParseError::DuplicateResourceKey(ref k)) if k == "a"
+        ));
+    }
+}
@@ -0,0 +1,1159 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use log::warn;
+
+use crate::brush::{Brush, Gradient, MappingMode, SpreadMethod, TileBrush, TileMode};
+use crate::color::Color;
+use crate::error::RenderResult;
+use crate::font;
+use crate::geometry::{FillRule, Geometry, PathBuilder, Point};
+use crate::image::Image;
+use crate::renderer::{
+    Canvas, Clip, Glyphs, LineCap, LineJoin, Opacity, OpacityMask, Path, Rect, RenderTransform,
+    Renderer,
+};
+
+/// SVG renderer, writing a page as an SVG document. Glyphs are
+/// written as outlines and images are embedded as data URIs.
+pub struct SvgRenderer {
+    width: f64,
+    height: f64,
+    size: (f64, f64),
+    background: Option<(f64, f64, f64, f64)>,
+    transform: Cell<RenderTransform>,
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    defs: String,
+    // The content being written: the page, followed by the content
+    // of the visual brushes being rendered
+    content: Vec<String>,
+    next_id: u32,
+    // The images already embedded in the defs, with their id
+    images: Vec<(Arc<Image>, String)>,
+}
+
+impl SvgRenderer {
+    /// Returns a renderer for a page of the given size
+    pub fn new(width: f64, height: f64) -> Self {
+        let state = State {
+            content: vec![String::new()],
+            ..State::default()
+        };
+
+        Self {
+            width,
+            height,
+            size: (width, height),
+            background: None,
+            transform: Cell::new(RenderTransform::identity()),
+            state: RefCell::new(state),
+        }
+    }
+
+    /// Sets the size of the SVG image, by default the size of the
+    /// page. The page is scaled to the given size.
+    pub fn set_size(&mut self, width: f64, height: f64) {
+        self.size = (width, height);
+    }
+
+    /// Sets the color painted under the page, with components
+    /// between 0 and 1
+    pub fn set_background(&mut self, red: f64, green: f64, blue: f64, alpha: f64) {
+        self.background = Some((red, green, blue, alpha));
+    }
+
+    /// Returns the SVG document with what has been rendered
+    pub fn into_svg(self) -> String {
+        let state = self.state.into_inner();
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+             width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" \
+             preserveAspectRatio=\"none\">\n",
+            self.size.0, self.size.1, self.width, self.height
+        );
+
+        if !state.defs.is_empty() {
+            let _ = write!(svg, "<defs>\n{}</defs>\n", state.defs);
+        }
+
+        if let Some((r, g, b, a)) = self.background {
+            let _ = writeln!(
+                svg,
+                "<rect width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+                self.width,
+                self.height,
+                rgb(r, g, b),
+                a
+            );
+        }
+
+        svg.push_str(&state.content[0]);
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+
+    fn new_id(&self, prefix: &str) -> String {
+        let mut state = self.state.borrow_mut();
+        state.next_id += 1;
+
+        format!("{}{}", prefix, state.next_id)
+    }
+
+    fn add_def(&self, def: &str) {
+        self.state.borrow_mut().defs.push_str(def);
+    }
+
+    fn write(&self, s: &str) {
+        if let Some(content) = self.state.borrow_mut().content.last_mut() {
+            content.push_str(s);
+        }
+    }
+
+    // Renders the content of an element with `draw`, in a group with
+    // the transform, clip, opacity and opacity mask of the element.
+    // The mask brush is mapped to the bounds of the element, or to
+    // the bounds of the page when the element has no geometry.
+    fn with_group<F>(
+        &self,
+        transform: Option<RenderTransform>,
+        clip: &Option<Clip>,
+        opacity: Option<Opacity>,
+        opacity_mask: &Option<OpacityMask>,
+        bounds: Option<&Geometry>,
+        draw: F,
+    ) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let saved = self.transform.get();
+        let res = self.write_group(transform, clip, opacity, opacity_mask, bounds, draw);
+        self.transform.set(saved);
+
+        res
+    }
+
+    fn write_group<F>(
+        &self,
+        transform: Option<RenderTransform>,
+        clip: &Option<Clip>,
+        opacity: Option<Opacity>,
+        opacity_mask: &Option<OpacityMask>,
+        bounds: Option<&Geometry>,
+        draw: F,
+    ) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let mut attributes = String::new();
+
+        if let Some(t) = transform {
+            self.transform.set(t.multiply(&self.transform.get()));
+            let _ = write!(attributes, " transform=\"{}\"", matrix(&t));
+        }
+
+        if let Some(Clip(ref geometry)) = clip {
+            let id = self.new_id("clip");
+            self.add_def(&format!(
+                "<clipPath id=\"{}\" clipPathUnits=\"userSpaceOnUse\">\
+                 <path d=\"{}\" clip-rule=\"{}\"/></clipPath>\n",
+                id,
+                path_data(geometry, false),
+                fill_rule(geometry.fill_rule)
+            ));
+            let _ = write!(attributes, " clip-path=\"url(#{})\"", id);
+        }
+
+        // An unsupported mask brush is ignored
+        if let Some(OpacityMask(ref brush)) = opacity_mask {
+            if let Some(id) = self.opacity_mask(brush, bounds)? {
+                let _ = write!(attributes, " mask=\"url(#{})\"", id);
+            }
+        }
+
+        if let Some(Opacity(opacity)) = opacity {
+            if opacity < 1.0 {
+                let _ = write!(attributes, " opacity=\"{}\"", opacity);
+            }
+        }
+
+        if attributes.is_empty() {
+            return draw();
+        }
+
+        self.write(&format!("<g{}>\n", attributes));
+        let res = draw();
+        self.write("</g>\n");
+
+        res
+    }
+
+    // Returns the id of a mask with the alpha of the brush painted
+    // over the whole page
+    fn opacity_mask(
+        &self,
+        brush: &Brush,
+        bounds: Option<&Geometry>,
+    ) -> RenderResult<Option<String>> {
+        let target = match self.user_bounds() {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+
+        let bounds = match bounds {
+            Some(geometry) => match geometry_bounds(geometry, false) {
+                Some(bounds) => bounds,
+                None => return Ok(None),
+            },
+            None => target,
+        };
+
+        let (paint, opacity) = match self.paint(brush, bounds)? {
+            Some(paint) => paint,
+            None => return Ok(None),
+        };
+
+        let id = self.new_id("mask");
+        self.add_def(&format!(
+            "<mask id=\"{id}\" maskUnits=\"userSpaceOnUse\" style=\"mask-type:alpha\" \
+             x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\">\
+             <rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\" fill=\"{paint}\"{opacity}/>\
+             </mask>\n",
+            id = id,
+            x = target.x,
+            y = target.y,
+            w = target.width,
+            h = target.height,
+            paint = paint,
+            opacity = opacity_attribute("fill-opacity", opacity)
+        ));
+
+        Ok(Some(id))
+    }
+
+    // Returns the bounds of the page in user space
+    fn user_bounds(&self) -> Option<Rect> {
+        let inverse = invert(&self.transform.get())?;
+
+        let page = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: self.width,
+            height: self.height,
+        };
+
+        let mut builder = BoundsBuilder::new(Some(inverse));
+        builder.add_rect(page);
+
+        builder.bounds()
+    }
+
+    // Returns the paint of the brush and its opacity, `bounds` are the
+    // bounds of the geometry in user space. Returns None if the brush
+    // is not supported or does not paint anything.
+    fn paint(&self, brush: &Brush, bounds: Rect) -> RenderResult<Option<(String, f64)>> {
+        match brush {
+            Brush::SolidColor(b) => Ok(Some((color(&b.color), b.color.alpha() * b.opacity))),
+            Brush::LinearGradient(b) => {
+                let g = &b.gradient;
+                let transform = brush_matrix(
+                    RenderTransform::identity(),
+                    g.mapping_mode,
+                    g.transform,
+                    bounds,
+                );
+
+                let (p0, p1) = (b.start_point, b.end_point);
+                let attributes = format!(
+                    "x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                    p0.x, p0.y, p1.x, p1.y
+                );
+
+                Ok(self.gradient("linearGradient", &attributes, g, &transform))
+            }
+            Brush::RadialGradient(b) => {
+                if b.radius_x <= 0.0 || b.radius_y <= 0.0 {
+                    return Ok(None);
+                }
+
+                // The gradient is built in a space where the ellipse is
+                // a circle of radius RadiusX and then scaled vertically
+                let k = b.radius_x / b.radius_y;
+                let (o, c) = (b.gradient_origin, b.center);
+
+                let g = &b.gradient;
+                let scale = RenderTransform {
+                    yy: 1.0 / k,
+                    ..RenderTransform::identity()
+                };
+                let transform = brush_matrix(scale, g.mapping_mode, g.transform, bounds);
+
+                let attributes = format!(
+                    "cx=\"{}\" cy=\"{}\" r=\"{}\" fx=\"{}\" fy=\"{}\"",
+                    c.x,
+                    c.y * k,
+                    b.radius_x,
+                    o.x,
+                    o.y * k
+                );
+
+                Ok(self.gradient("radialGradient", &attributes, g, &transform))
+            }
+            Brush::Image(b) => {
+                let image = match b.image {
+                    Some(ref image) => image,
+                    None => return Ok(None),
+                };
+
+                let id = match self.image(image) {
+                    Some(id) => id,
+                    None => return Ok(None),
+                };
+
+                let content = format!("<use xlink:href=\"#{}\"/>", id);
+
+                Ok(self.tile_pattern(&b.tile, bounds, |_| content))
+            }
+            Brush::Visual(b) => {
+                let mut res = Ok(());
+
+                let pattern = self.tile_pattern(&b.tile, bounds, |transform| {
+                    let saved = self.transform.replace(transform);
+                    self.state.borrow_mut().content.push(String::new());
+
+                    res = self.render_children(&b.visual);
+
+                    self.transform.set(saved);
+                    self.state.borrow_mut().content.pop().unwrap_or_default()
+                });
+
+                res.map(|_| pattern)
+            }
+        }
+    }
+
+    // Adds the definition of a gradient, the transform maps the space
+    // of the gradient to user space
+    fn gradient(
+        &self,
+        tag: &str,
+        attributes: &str,
+        gradient: &Gradient,
+        transform: &RenderTransform,
+    ) -> Option<(String, f64)> {
+        if gradient.gradient_stops.is_empty() || invert(transform).is_none() {
+            return None;
+        }
+
+        let id = self.new_id("gradient");
+
+        let mut def = format!(
+            "<{} id=\"{}\" gradientUnits=\"userSpaceOnUse\" {} spreadMethod=\"{}\" \
+             gradientTransform=\"{}\">",
+            tag,
+            id,
+            attributes,
+            spread_method(gradient.spread_method),
+            matrix(transform)
+        );
+
+        for (offset, c) in gradient.color_stops() {
+            let _ = write!(
+                def,
+                "<stop offset=\"{}\" stop-color=\"{}\"{}/>",
+                offset,
+                color(&c),
+                opacity_attribute("stop-opacity", c.alpha() * gradient.opacity)
+            );
+        }
+
+        let _ = writeln!(def, "</{}>", tag);
+        self.add_def(&def);
+
+        Some((format!("url(#{})", id), 1.0))
+    }
+
+    // Returns the id of the image in the defs, embedding it the
+    // first time it is used
+    fn image(&self, image: &Arc<Image>) -> Option<String> {
+        let embedded = self
+            .state
+            .borrow()
+            .images
+            .iter()
+            .find(|(i, _)| Arc::ptr_eq(i, image))
+            .map(|(_, id)| id.clone());
+
+        if embedded.is_some() {
+            return embedded;
+        }
+
+        let (pixel_width, pixel_height) = image.pixel_size();
+        let mut png = Vec::new();
+
+        let encoder = image::png::PngEncoder::new(&mut png);
+        if let Err(e) = encoder.encode(
+            image.pixels(),
+            pixel_width,
+            pixel_height,
+            image::ColorType::Rgba8,
+        ) {
+            warn!("Failed to encode image: {}", e);
+            return None;
+        }
+
+        // The image is sized in 1/96 inch, the units of the viewbox
+        let (width, height) = image.size();
+
+        let id = self.new_id("image");
+        self.add_def(&format!(
+            "<image id=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" \
+             xlink:href=\"data:image/png;base64,{}\"/>\n",
+            id,
+            width,
+            height,
+            base64::encode(&png)
+        ));
+
+        self.state
+            .borrow_mut()
+            .images
+            .push((image.clone(), id.clone()));
+
+        Some(id)
+    }
+
+    // Adds the definition of a pattern repeating the tile. The content
+    // is returned by `draw` in the coordinate space of the viewbox,
+    // given the transform from that space to the page.
+    fn tile_pattern<F>(&self, tile: &TileBrush, bounds: Rect, draw: F) -> Option<(String, f64)>
+    where
+        F: FnOnce(RenderTransform) -> String,
+    {
+        let (vb, vp) = (tile.viewbox, tile.viewport);
+        if vb.width <= 0.0 || vb.height <= 0.0 || vp.width <= 0.0 || vp.height <= 0.0 {
+            return None;
+        }
+
+        let transform = brush_matrix(
+            RenderTransform::identity(),
+            tile.viewport_units,
+            tile.transform,
+            bounds,
+        );
+        let inverse = invert(&transform)?;
+
+        let (columns, rows) = match tile.tile_mode {
+            TileMode::None | TileMode::Tile => (1, 1),
+            TileMode::FlipX => (2, 1),
+            TileMode::FlipY => (1, 2),
+            TileMode::FlipXY => (2, 2),
+        };
+
+        // Patterns always repeat, so a single tile is drawn in a
+        // pattern large enough to cover the whole geometry
+        let area = match tile.tile_mode {
+            TileMode::None => {
+                let mut builder = BoundsBuilder::new(Some(inverse));
+                builder.add_rect(bounds);
+                builder.transform = None;
+                builder.add_rect(vp);
+                builder.bounds()?
+            }
+            _ => Rect {
+                x: vp.x,
+                y: vp.y,
+                width: vp.width * f64::from(columns),
+                height: vp.height * f64::from(rows),
+            },
+        };
+
+        let viewbox = RenderTransform {
+            xx: vp.width / vb.width,
+            yx: 0.0,
+            xy: 0.0,
+            yy: vp.height / vb.height,
+            x0: vp.x - vb.x * vp.width / vb.width,
+            y0: vp.y - vb.y * vp.height / vb.height,
+        };
+        let to_page = viewbox.multiply(&transform).multiply(&self.transform.get());
+
+        let content = draw(to_page);
+
+        let symbol = self.new_id("tile");
+        self.add_def(&format!(
+            "<symbol id=\"{}\" viewBox=\"{} {} {} {}\" preserveAspectRatio=\"none\">\n{}</symbol>\n",
+            symbol, vb.x, vb.y, vb.width, vb.height, content
+        ));
+
+        let id = self.new_id("pattern");
+        let mut def = format!(
+            "<pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" x=\"{}\" y=\"{}\" \
+             width=\"{}\" height=\"{}\" patternTransform=\"{}\"><g{}>",
+            id,
+            area.x,
+            area.y,
+            area.width,
+            area.height,
+            matrix(&transform),
+            opacity_attribute("opacity", tile.opacity)
+        );
+
+        // Odd columns and rows are mirrored images of the tile. The
+        // content of the pattern is relative to the origin of its area.
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut cell = RenderTransform {
+                    x0: vp.x - area.x + f64::from(column) * vp.width,
+                    y0: vp.y - area.y + f64::from(row) * vp.height,
+                    ..RenderTransform::identity()
+                };
+                if column == 1 {
+                    cell.xx = -1.0;
+                    cell.x0 += vp.width;
+                }
+                if row == 1 {
+                    cell.yy = -1.0;
+                    cell.y0 += vp.height;
+                }
+
+                let _ = write!(
+                    def,
+                    "<use xlink:href=\"#{}\" width=\"{}\" height=\"{}\" transform=\"{}\"/>",
+                    symbol,
+                    vp.width,
+                    vp.height,
+                    matrix(&cell)
+                );
+            }
+        }
+
+        def.push_str("</g></pattern>\n");
+        self.add_def(&def);
+
+        Some((format!("url(#{})", id), 1.0))
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn render_canvas(&self, canvas: &Canvas) -> RenderResult<()> {
+        self.with_group(
+            canvas.render_transform,
+            &canvas.clip,
+            canvas.opacity,
+            &canvas.opacity_mask,
+            None,
+            || self.render_children(&canvas.children),
+        )
+    }
+
+    fn render_glyphs(&self, glyphs: &Glyphs) -> RenderResult<()> {
+        let fill = match glyphs.fill {
+            Some(ref fill) => fill,
+            None => return Ok(()),
+        };
+
+        let outline = match font::outline(glyphs) {
+            Some(outline) => outline,
+            None => return Ok(()),
+        };
+
+        self.with_group(
+            glyphs.render_transform,
+            &glyphs.clip,
+            glyphs.opacity,
+            &glyphs.opacity_mask,
+            Some(&outline),
+            || {
+                let bounds = match geometry_bounds(&outline, false) {
+                    Some(bounds) => bounds,
+                    None => return Ok(()),
+                };
+
+                let (paint, opacity) = match self.paint(&fill.0, bounds)? {
+                    Some(paint) => paint,
+                    None => return Ok(()),
+                };
+
+                let mut element = format!(
+                    "<path d=\"{}\" fill=\"{}\"{} fill-rule=\"{}\"",
+                    path_data(&outline, false),
+                    paint,
+                    opacity_attribute("fill-opacity", opacity),
+                    fill_rule(outline.fill_rule)
+                );
+
+                // The bold simulation is emulated by stroking the outline
+                if let Some(width) = font::bold_stroke_width(glyphs) {
+                    let _ = write!(
+                        element,
+                        " stroke=\"{}\"{} stroke-width=\"{}\" stroke-linejoin=\"round\"",
+                        paint,
+                        opacity_attribute("stroke-opacity", opacity),
+                        width
+                    );
+                }
+
+                element.push_str("/>\n");
+                self.write(&element);
+
+                Ok(())
+            },
+        )
+    }
+
+    fn render_path(&self, path: &Path) -> RenderResult<()> {
+        let data = match path.data {
+            Some(ref data) => data,
+            None => return Ok(()),
+        };
+
+        self.with_group(
+            path.render_transform,
+            &path.clip,
+            path.opacity,
+            &path.opacity_mask,
+            Some(data),
+            || {
+                if let Some(ref fill) = path.fill {
+                    if let Some(bounds) = geometry_bounds(data, false) {
+                        if let Some((paint, opacity)) = self.paint(&fill.0, bounds)? {
+                            self.write(&format!(
+                                "<path d=\"{}\" fill=\"{}\"{} fill-rule=\"{}\"/>\n",
+                                path_data(data, false),
+                                paint,
+                                opacity_attribute("fill-opacity", opacity),
+                                fill_rule(data.fill_rule)
+                            ));
+                        }
+                    }
+                }
+
+                if let Some(ref stroke) = path.stroke {
+                    let thickness = path.stroke_thickness.unwrap_or_default().0;
+                    if thickness <= 0.0 {
+                        return Ok(());
+                    }
+
+                    if let Some(bounds) = geometry_bounds(data, true) {
+                        if let Some((paint, opacity)) = self.paint(&stroke.0, bounds)? {
+                            self.write(&format!(
+                                "<path d=\"{}\" fill=\"none\" stroke=\"{}\"{}{}/>\n",
+                                path_data(data, true),
+                                paint,
+                                opacity_attribute("stroke-opacity", opacity),
+                                stroke_style(path)
+                            ));
+                        }
+                    }
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+impl std::fmt::Debug for SvgRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SvgRenderer")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+fn stroke_style(path: &Path) -> String {
+    let thickness = path.stroke_thickness.unwrap_or_default().0;
+
+    // SVG does not support different caps for the start and the end
+    // of a line, nor triangle caps
+    let cap = path.line_cap();
+    let join = path.stroke_line_join.map(|j| j.0).unwrap_or_default();
+    let miter_limit = path.stroke_miter_limit.unwrap_or_default();
+
+    let mut style = format!(
+        " stroke-width=\"{}\" stroke-linecap=\"{}\" stroke-linejoin=\"{}\" \
+         stroke-miterlimit=\"{}\"",
+        thickness,
+        match cap {
+            LineCap::Flat => "butt",
+            LineCap::Round | LineCap::Triangle => "round",
+            LineCap::Square => "square",
+        },
+        match join {
+            LineJoin::Miter => "miter",
+            LineJoin::Bevel => "bevel",
+            LineJoin::Round => "round",
+        },
+        miter_limit.0.max(1.0)
+    );
+
+    // Dashes are expressed in multiples of the stroke thickness
+    if let Some(ref dashes) = path.stroke_dash_array {
+        if !dashes.0.is_empty() && dashes.0.iter().any(|&d| d > 0.0) {
+            let dashes = dashes
+                .0
+                .iter()
+                .map(|d| (d * thickness).to_string())
+                .collect::<Vec<_>>();
+            let offset = path.stroke_dash_offset.map(|o| o.0).unwrap_or_default();
+
+            let _ = write!(
+                style,
+                " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"",
+                dashes.join(" "),
+                offset * thickness
+            );
+        }
+    }
+
+    style
+}
+
+fn opacity_attribute(name: &str, opacity: f64) -> String {
+    if opacity < 1.0 {
+        format!(" {}=\"{}\"", name, opacity.max(0.0))
+    } else {
+        String::new()
+    }
+}
+
+fn color(color: &Color) -> String {
+    let (r, g, b, _) = color.to_srgb8();
+
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn rgb(r: f64, g: f64, b: f64) -> String {
+    let c = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    format!("#{:02x}{:02x}{:02x}", c(r), c(g), c(b))
+}
+
+fn fill_rule(rule: FillRule) -> &'static str {
+    match rule {
+        FillRule::EvenOdd => "evenodd",
+        FillRule::NonZero => "nonzero",
+    }
+}
+
+fn spread_method(method: SpreadMethod) -> &'static str {
+    match method {
+        SpreadMethod::Pad => "pad",
+        SpreadMethod::Reflect => "reflect",
+        SpreadMethod::Repeat => "repeat",
+    }
+}
+
+fn matrix(t: &RenderTransform) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        t.xx, t.yx, t.xy, t.yy, t.x0, t.y0
+    )
+}
+
+fn invert(t: &RenderTransform) -> Option<RenderTransform> {
+    let det = t.xx * t.yy - t.yx * t.xy;
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    Some(RenderTransform {
+        xx: t.yy / det,
+        yx: -t.yx / det,
+        xy: -t.xy / det,
+        yy: t.xx / det,
+        x0: (t.xy * t.y0 - t.yy * t.x0) / det,
+        y0: (t.yx * t.x0 - t.xx * t.y0) / det,
+    })
+}
+
+// Returns the transform mapping the brush space to user space, given
+// the transform that maps the pattern space to the brush space
+fn brush_matrix(
+    transform: RenderTransform,
+    mapping_mode: MappingMode,
+    brush_transform: Option<RenderTransform>,
+    bounds: Rect,
+) -> RenderTransform {
+    let mut transform = transform;
+
+    // Relative coordinates are mapped to the bounding box of
+    // the geometry, before applying the brush transform
+    if mapping_mode == MappingMode::RelativeToBoundingBox {
+        let bbox = RenderTransform {
+            xx: bounds.width,
+            yx: 0.0,
+            xy: 0.0,
+            yy: bounds.height,
+            x0: bounds.x,
+            y0: bounds.y,
+        };
+        transform = transform.multiply(&bbox);
+    }
+
+    if let Some(t) = brush_transform {
+        transform = transform.multiply(&t);
+    }
+
+    transform
+}
+
+// Returns the path data of the geometry, in the coordinate space of
+// the element. The geometry transform only affects the coordinates
+// of the path, not the stroke.
+fn path_data(geometry: &Geometry, stroke: bool) -> String {
+    let mut builder = SvgPathBuilder {
+        data: String::new(),
+        transform: geometry.transform,
+    };
+
+    if stroke {
+        geometry.build_stroke(&mut builder);
+    } else {
+        geometry.build_fill(&mut builder);
+    }
+
+    builder.data.trim_start().to_string()
+}
+
+fn geometry_bounds(geometry: &Geometry, stroke: bool) -> Option<Rect> {
+    let mut builder = BoundsBuilder::new(geometry.transform);
+
+    if stroke {
+        geometry.build_stroke(&mut builder);
+    } else {
+        geometry.build_fill(&mut builder);
+    }
+
+    builder.bounds()
+}
+
+struct SvgPathBuilder {
+    data: String,
+    transform: Option<RenderTransform>,
+}
+
+impl SvgPathBuilder {
+    fn point(&mut self, p: Point) {
+        let p = match self.transform {
+            Some(ref t) => t.transform_point(p),
+            None => p,
+        };
+
+        let _ = write!(self.data, " {},{}", p.x, p.y);
+    }
+}
+
+impl PathBuilder for SvgPathBuilder {
+    fn move_to(&mut self, p: Point) {
+        self.data.push_str(" M");
+        self.point(p);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.data.push_str(" L");
+        self.point(p);
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.data.push_str(" C");
+        self.point(c1);
+        self.point(c2);
+        self.point(p);
+    }
+
+    fn close(&mut self) {
+        self.data.push_str(" Z");
+    }
+}
+
+// Computes the bounds of a path, including the extrema of its curves
+struct BoundsBuilder {
+    transform: Option<RenderTransform>,
+    current: Point,
+    extents: Option<(Point, Point)>,
+}
+
+impl BoundsBuilder {
+    fn new(transform: Option<RenderTransform>) -> Self {
+        Self {
+            transform,
+            current: Point::default(),
+            extents: None,
+        }
+    }
+
+    fn transform(&self, p: Point) -> Point {
+        match self.transform {
+            Some(ref t) => t.transform_point(p),
+            None => p,
+        }
+    }
+
+    fn add(&mut self, p: Point) {
+        let (min, max) = self.extents.get_or_insert((p, p));
+
+        min.x = min.x.min(p.x);
+        min.y = min.y.min(p.y);
+        max.x = max.x.max(p.x);
+        max.y = max.y.max(p.y);
+    }
+
+    fn add_rect(&mut self, r: Rect) {
+        for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            self.move_to(Point::new(r.x + x * r.width, r.y + y * r.height));
+        }
+    }
+
+    fn bounds(&self) -> Option<Rect> {
+        self.extents.map(|(min, max)| Rect {
+            x: min.x,
+            y: min.y,
+            width: max.x - min.x,
+            height: max.y - min.y,
+        })
+    }
+}
+
+impl PathBuilder for BoundsBuilder {
+    fn move_to(&mut self, p: Point) {
+        self.current = self.transform(p);
+        self.add(self.current);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.move_to(p);
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        let p0 = self.current;
+        let (p1, p2, p3) = (self.transform(c1), self.transform(c2), self.transform(p));
+
+        for t in cubic_extrema(p0.x, p1.x, p2.x, p3.x)
+            .into_iter()
+            .chain(cubic_extrema(p0.y, p1.y, p2.y, p3.y))
+        {
+            let u = 1.0 - t;
+            let at = |a: f64, b: f64, c: f64, d: f64| {
+                u * u * u * a + 3.0 * u * u * t * b + 3.0 * u * t * t * c + t * t * t * d
+            };
+
+            self.add(Point::new(
+                at(p0.x, p1.x, p2.x, p3.x),
+                at(p0.y, p1.y, p2.y, p3.y),
+            ));
+        }
+
+        self.current = p3;
+        self.add(p3);
+    }
+
+    fn close(&mut self) {}
+}
+
+// Returns the parameters in (0, 1) where the derivative of the cubic
+// bezier with the given coordinates is zero
+fn cubic_extrema(p0: f64, p1: f64, p2: f64, p3: f64) -> Vec<f64> {
+    let (d0, d1, d2) = (p1 - p0, p2 - p1, p3 - p2);
+    let (a, b, c) = (d0 - 2.0 * d1 + d2, 2.0 * (d1 - d0), d0);
+
+    let roots = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON {
+            vec![]
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            vec![]
+        } else {
+            let sqrt = discriminant.sqrt();
+            vec![(-b + sqrt) / (2.0 * a), (-b - sqrt) / (2.0 * a)]
+        }
+    };
+
+    roots.into_iter().filter(|&t| t > 0.0 && t < 1.0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parts::FixedPage;
+
+    fn render(content: &str) -> String {
+        let xml = format!(
+            r#"<FixedPage xmlns="http://schemas.microsoft.com/xps/2005/06" Width="100" Height="50">{}</FixedPage>"#,
+            content
+        );
+        let page = FixedPage::parse(&xml, &|_| unreachable!()).unwrap();
+
+        let renderer = SvgRenderer::new(100.0, 50.0);
+        renderer.render(&page.render_tree).unwrap();
+
+        renderer.into_svg()
+    }
+
+    fn assert_near(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn fill_path() {
+        let svg = render(
+            r##"<Path Data="F 1 M 10,10 L 20,10 L 20,20 Z" Fill="#80FF0000"/>
+                <Path Data="M 0,0 L 5,5" Fill="#0000FF"/>"##,
+        );
+
+        assert!(svg.contains(
+            r##"<path d="M 10,10 L 20,10 L 20,20 Z" fill="#ff0000" fill-opacity="0.5019607843137255" fill-rule="nonzero"/>"##
+        ));
+        assert!(svg.contains(r##"<path d="M 0,0 L 5,5" fill="#0000ff" fill-rule="evenodd"/>"##));
+        assert!(!svg.contains("<defs>"));
+    }
+
+    #[test]
+    fn clip() {
+        let svg = render(
+            r##"<Canvas Clip="M 0,0 L 50,0 L 50,50 Z">
+                  <Path Data="M 10,10 L 20,10 L 20,20 Z" Fill="#FF0000"/>
+                </Canvas>"##,
+        );
+
+        assert!(svg.contains(
+            r#"<clipPath id="clip1" clipPathUnits="userSpaceOnUse"><path d="M 0,0 L 50,0 L 50,50 Z" clip-rule="evenodd"/></clipPath>"#
+        ));
+        assert!(svg.contains(r#"<g clip-path="url(#clip1)">"#));
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let svg = render(
+            r##"<Path Data="M 10,20 L 30,20 L 30,60 Z">
+                  <Path.Fill>
+                    <LinearGradientBrush MappingMode="RelativeToBoundingBox" StartPoint="0,0"
+                        EndPoint="1,0" SpreadMethod="Reflect">
+                      <LinearGradientBrush.GradientStops>
+                        <GradientStop Color="#FF0000" Offset="0"/>
+                        <GradientStop Color="#800000FF" Offset="1"/>
+                      </LinearGradientBrush.GradientStops>
+                    </LinearGradientBrush>
+                  </Path.Fill>
+                </Path>"##,
+        );
+
+        // The gradient is mapped to the bounds of the path
+        assert!(svg.contains(
+            r#"<linearGradient id="gradient1" gradientUnits="userSpaceOnUse" x1="0" y1="0" x2="1" y2="0" spreadMethod="reflect" gradientTransform="matrix(20 0 0 40 10 20)">"#
+        ));
+        assert!(svg.contains(r##"<stop offset="0" stop-color="#ff0000"/>"##));
+        assert!(svg.contains(
+            r##"<stop offset="1" stop-color="#0000ff" stop-opacity="0.5019607843137255"/>"##
+        ));
+        assert!(svg.contains(r#"fill="url(#gradient1)""#));
+    }
+
+    #[test]
+    fn tile_flip_xy() {
+        let svg = render(
+            r##"<Path Data="M 0,0 L 100,0 L 100,50 L 0,50 Z">
+                  <Path.Fill>
+                    <VisualBrush Viewbox="0,0,10,10" Viewport="0,0,20,10" TileMode="FlipXY">
+                      <VisualBrush.Visual>
+                        <Path Data="M 0,0 L 10,0 L 10,5 Z" Fill="#FF0000"/>
+                      </VisualBrush.Visual>
+                    </VisualBrush>
+                  </Path.Fill>
+                </Path>"##,
+        );
+
+        // The content of the visual goes in the symbol, not the page
+        assert!(
+            svg.contains(r#"<symbol id="tile1" viewBox="0 0 10 10" preserveAspectRatio="none">"#)
+        );
+        assert_eq!(svg.matches(r##"fill="#ff0000""##).count(), 1);
+
+        assert!(svg.contains(
+            r#"<pattern id="pattern2" patternUnits="userSpaceOnUse" x="0" y="0" width="40" height="20" patternTransform="matrix(1 0 0 1 0 0)">"#
+        ));
+
+        let cells = [
+            "matrix(1 0 0 1 0 0)",
+            "matrix(-1 0 0 1 40 0)",
+            "matrix(1 0 0 -1 0 20)",
+            "matrix(-1 0 0 -1 40 20)",
+        ];
+        for cell in cells.iter() {
+            assert!(svg.contains(&format!(
+                r##"<use xlink:href="#tile1" width="20" height="10" transform="{}"/>"##,
+                cell
+            )));
+        }
+        assert_eq!(svg.matches("<use ").count(), 4);
+    }
+
+    #[test]
+    fn extrema() {
+        assert!(cubic_extrema(0.0, 1.0, 2.0, 3.0).is_empty());
+        assert!(cubic_extrema(0.0, 0.0, 0.0, 0.0).is_empty());
+
+        let t = cubic_extrema(0.0, 1.0, 1.0, 0.0);
+        assert_eq!(t.len(), 1);
+        assert_near(t[0], 0.5);
+
+        let mut t = cubic_extrema(0.0, 3.0, -3.0, 0.0);
+        t.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(t.len(), 2);
+        assert_near(t[0], 0.5 - 3.0f64.sqrt() / 6.0);
+        assert_near(t[1], 0.5 + 3.0f64.sqrt() / 6.0);
+
+        // Extrema outside of the curve are ignored
+        assert!(cubic_extrema(0.0, 2.0, 3.0, 3.5).is_empty());
+    }
+
+    #[test]
+    fn bounds() {
+        let curve = |builder: &mut BoundsBuilder| {
+            builder.move_to(Point::new(0.0, 0.0));
+            builder.curve_to(
+                Point::new(0.0, 10.0),
+                Point::new(10.0, 10.0),
+                Point::new(10.0, 0.0),
+            );
+        };
+
+        // The curve goes beyond its end points, but not up to its
+        // control points
+        let mut builder = BoundsBuilder::new(None);
+        curve(&mut builder);
+        let b = builder.bounds().unwrap();
+        assert_eq!((b.x, b.y, b.width, b.height), (0.0, 0.0, 10.0, 7.5));
+
+        let t = RenderTransform {
+            xx: 2.0,
+            yy: 2.0,
+            x0: 5.0,
+            ..RenderTransform::identity()
+        };
+        let mut builder = BoundsBuilder::new(Some(t));
+        curve(&mut builder);
+        builder.line_to(Point::new(-1.0, -2.0));
+        let b = builder.bounds().unwrap();
+        assert_eq!((b.x, b.y, b.width, b.height), (3.0, -4.0, 22.0, 19.0));
+
+        assert!(BoundsBuilder::new(None).bounds().is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::font;
+use crate::geometry::Point;
+use crate::renderer::{Glyphs, Rect, RenderNode, RenderTransform};
+
+// Metrics used when the font of a run is not available, relative
+// to the em size
+const FALLBACK_ASCENT: f64 = 0.8;
+const FALLBACK_DESCENT: f64 = 0.2;
+const FALLBACK_ADVANCE: f64 = 0.5;
+
+// Thresholds of the heuristics that lay out the text, relative to
+// the font size of the runs: the horizontal gap between two runs
+// that is a space, the distance between the baselines of runs on
+// the same line and the distance between lines that is a paragraph
+// break
+const SPACE_GAP: f64 = 0.15;
+const LINE_DISTANCE: f64 = 0.5;
+const PARAGRAPH_DISTANCE: f64 = 2.0;
+
+/// A run of text of a page, from a `Glyphs` element
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    pub(crate) text: String,
+    pub(crate) bounds: Rect,
+    pub(crate) rtl: bool,
+    // The origin of the baseline and the font size, in page units
+    pub(crate) origin: Point,
+    pub(crate) size: f64,
+}
+
+impl TextRun {
+    /// Returns the text of the run, in logical order
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the bounding box of the run in page units, as x, y,
+    /// width and height
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let b = &self.bounds;
+        (b.x, b.y, b.width, b.height)
+    }
+
+    /// Returns whether the run is laid out right to left
+    pub fn is_rtl(&self) -> bool {
+        self.rtl
+    }
+
+    fn left(&self) -> f64 {
+        self.bounds.x
+    }
+
+    fn right(&self) -> f64 {
+        self.bounds.x + self.bounds.width
+    }
+}
+
+/// Returns the text runs of the render tree, in reading order
+pub(crate) fn text_runs(node: &RenderNode) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    collect_runs(node, &RenderTransform::identity(), &mut runs);
+
+    lines(runs).into_iter().flatten().collect()
+}
+
+/// Returns the text of the render tree, in reading order, inserting
+/// spaces and newlines between the runs according to their position
+pub(crate) fn text(node: &RenderNode) -> String {
+    let mut runs = Vec::new();
+    collect_runs(node, &RenderTransform::identity(), &mut runs);
+
+    let mut res = String::new();
+    let mut previous_line: Option<&[TextRun]> = None;
+
+    let lines = lines(runs);
+    for line in lines.iter() {
+        if let Some(previous) = previous_line {
+            res.push('\n');
+
+            // Lines further apart than usual start a new paragraph
+            let size = line_size(line).max(line_size(previous));
+            if line[0].origin.y - previous[0].origin.y > PARAGRAPH_DISTANCE * size {
+                res.push('\n');
+            }
+        }
+
+        for (i, run) in line.iter().enumerate() {
+            if i > 0 {
+                // The runs are ordered in either direction
+                let previous = &line[i - 1];
+                let gap = (run.left() - previous.right()).max(previous.left() - run.right());
+
+                let size = run.size.min(previous.size);
+                if gap > SPACE_GAP * size
+                    && !previous.text.ends_with(char::is_whitespace)
+                    && !run.text.starts_with(char::is_whitespace)
+                {
+                    res.push(' ');
+                }
+            }
+
+            res.push_str(&run.text);
+        }
+
+        previous_line = Some(line);
+    }
+
+    res
+}
+
+// Collects the runs of the Glyphs elements, with their transform
+// to the page. The content of visual brushes is not text.
+fn collect_runs(node: &RenderNode, transform: &RenderTransform, runs: &mut Vec<TextRun>) {
+    match node {
+        RenderNode::Root(children) => {
+            for c in children.iter() {
+                collect_runs(c, transform, runs);
+            }
+        }
+        RenderNode::Canvas(canvas) => {
+            let transform = match canvas.render_transform {
+                Some(t) => t.multiply(transform),
+                None => *transform,
+            };
+
+            for c in canvas.children.iter() {
+                collect_runs(c, &transform, runs);
+            }
+        }
+        RenderNode::Glyphs(glyphs) => {
+            if let Some(run) = text_run(glyphs, transform) {
+                runs.push(run);
+            }
+        }
+        RenderNode::Path(_) => (),
+    }
+}
+
+fn text_run(glyphs: &Glyphs, transform: &RenderTransform) -> Option<TextRun> {
+    let text = glyphs.unicode_string.as_ref().map(|s| &s.0)?;
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    let transform = match glyphs.render_transform {
+        Some(t) => t.multiply(transform),
+        None => *transform,
+    };
+
+    let em = glyphs.font_rendering_em_size;
+    let (x, y) = glyphs.origin;
+    let rtl = glyphs.is_rtl();
+
+    // The horizontal extents of the glyphs and the vertical extents
+    // of the font, in the coordinate space of the element
+    let placed = glyphs
+        .font
+        .as_ref()
+        .map(|font| (font, font::layout(glyphs, font)))
+        .filter(|(_, placed)| !placed.is_empty());
+
+    let (left, right, ascent, descent) = match placed {
+        Some((font, placed)) => {
+            let left = placed.iter().map(|g| g.origin.x).fold(f64::MAX, f64::min);
+            let right = placed
+                .iter()
+                .map(|g| g.origin.x + g.advance)
+                .fold(f64::MIN, f64::max);
+
+            let scale = em / font.units_per_em();
+            (left, right, font.ascent() * scale, font.descent() * scale)
+        }
+        None => {
+            let width = text.chars().count() as f64 * FALLBACK_ADVANCE * em;
+            let (left, right) = if rtl { (x - width, x) } else { (x, x + width) };
+
+            (left, right, FALLBACK_ASCENT * em, FALLBACK_DESCENT * em)
+        }
+    };
+
+    let corners = [
+        Point::new(left, y - ascent),
+        Point::new(right, y - ascent),
+        Point::new(left, y + descent),
+        Point::new(right, y + descent),
+    ]
+    .iter()
+    .map(|&p| transform.transform_point(p))
+    .collect::<Vec<_>>();
+
+    let min_x = corners.iter().map(|p| p.x).fold(f64::MAX, f64::min);
+    let max_x = corners.iter().map(|p| p.x).fold(f64::MIN, f64::max);
+    let min_y = corners.iter().map(|p| p.y).fold(f64::MAX, f64::min);
+    let max_y = corners.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+
+    let det = transform.xx * transform.yy - transform.yx * transform.xy;
+
+    Some(TextRun {
+        text: text.clone(),
+        bounds: Rect {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+        },
+        rtl,
+        origin: transform.transform_point(Point::new(x, y)),
+        size: em * det.abs().sqrt(),
+    })
+}
+
+// Groups the runs in lines by their baseline, from top to bottom.
+// The runs of a line are ordered by their position, from right to
+// left when most of the text of the line is right to left.
+fn lines(mut runs: Vec<TextRun>) -> Vec<Vec<TextRun>> {
+    runs.sort_by(|a, b| a.origin.y.total_cmp(&b.origin.y));
+
+    let mut lines: Vec<Vec<TextRun>> = Vec::new();
+
+    for run in runs {
+        match lines.last_mut() {
+            Some(line)
+                if (run.origin.y - line[0].origin.y).abs()
+                    <= LINE_DISTANCE * run.size.min(line_size(line)) =>
+            {
+                line.push(run)
+            }
+            _ => lines.push(vec![run]),
+        }
+    }
+
+    for line in lines.iter_mut() {
+        let length = |rtl: bool| -> usize {
+            line.iter()
+                .filter(|r| r.rtl == rtl)
+                .map(|r| r.text.chars().count())
+                .sum()
+        };
+
+        if length(true) > length(false) {
+            line.sort_by(|a, b| b.right().total_cmp(&a.right()));
+        } else {
+            line.sort_by(|a, b| a.left().total_cmp(&b.left()));
+        }
+    }
+
+    lines
+}
+
+// Returns the smallest font size of the runs of the line
+fn line_size(line: &[TextRun]) -> f64 {
+    line.iter().map(|r| r.size).fold(f64::MAX, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{BidiLevel, UnicodeString};
+
+    // A run without font, so that each character is half the em size
+    // wide, extending to the left of the origin when right to left
+    fn glyphs(text: &str, x: f64, y: f64, rtl: bool) -> RenderNode {
+        RenderNode::Glyphs(Glyphs {
+            origin: (x, y),
+            font_rendering_em_size: 10.0,
+            unicode_string: Some(UnicodeString(String::from(text))),
+            bidi_level: if rtl { Some(BidiLevel(1)) } else { None },
+            ..Glyphs::default()
+        })
+    }
+
+    fn runs(nodes: Vec<RenderNode>) -> Vec<TextRun> {
+        let mut runs = Vec::new();
+        collect_runs(
+            &RenderNode::Root(nodes),
+            &RenderTransform::identity(),
+            &mut runs,
+        );
+
+        runs
+    }
+
+    #[test]
+    fn lines_by_baseline() {
+        let lines = lines(runs(vec![
+            glyphs("c", 0.0, 40.0, false),
+            glyphs("b", 20.0, 12.0, false),
+            glyphs("a", 0.0, 10.0, false),
+        ]));
+
+        // Baselines closer than half the font size are on the same line
+        let text = lines
+            .iter()
+            .map(|l| l.iter().map(TextRun::text).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(text, vec![vec!["a", "b"], vec!["c"]]);
+    }
+
+    #[test]
+    fn spaces() {
+        let root = RenderNode::Root(vec![
+            glyphs("world", 30.0, 10.0, false),
+            glyphs("Hello", 0.0, 10.0, false),
+            glyphs("Hel", 0.0, 30.0, false),
+            glyphs("lo", 15.0, 30.0, false),
+            glyphs("Hello ", 0.0, 50.0, false),
+            glyphs("world", 40.0, 50.0, false),
+        ]);
+
+        assert_eq!(text(&root), "Hello world\nHello\nHello world");
+    }
+
+    #[test]
+    fn paragraphs() {
+        let root = RenderNode::Root(vec![
+            glyphs("one", 0.0, 10.0, false),
+            glyphs("two", 0.0, 22.0, false),
+            glyphs("three", 0.0, 60.0, false),
+        ]);
+
+        assert_eq!(text(&root), "one\ntwo\n\nthree");
+    }
+
+    #[test]
+    fn right_to_left() {
+        // Most of the line is right to left, the runs are read from
+        // the right
+        let root = RenderNode::Root(vec![
+            glyphs("12", 60.0, 10.0, false),
+            glyphs("\u{5d0}\u{5d1}\u{5d2}\u{5d3}", 100.0, 10.0, true),
+            glyphs("\u{5d4}", 130.0, 10.0, true),
+        ]);
+        assert_eq!(text(&root), "\u{5d4} \u{5d0}\u{5d1}\u{5d2}\u{5d3} 12");
+
+        // Most of the line is left to right
+        let root = RenderNode::Root(vec![
+            glyphs("\u{5d0}\u{5d1}", 40.0, 10.0, true),
+            glyphs("Hello", 0.0, 10.0, false),
+        ]);
+        assert_eq!(text(&root), "Hello \u{5d0}\u{5d1}");
+
+        let runs = text_runs(&root);
+        assert!(!runs[0].is_rtl());
+        assert!(runs[1].is_rtl());
+        assert_eq!(runs[1].bounds(), (30.0, 2.0, 10.0, 10.0));
+    }
+}
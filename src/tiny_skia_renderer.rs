@@ -0,0 +1,1004 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use tiny_skia::{
+    FilterQuality, GradientStop, IntSize, LinearGradient, Mask, MaskType, Paint, Pattern, Pixmap,
+    PixmapPaint, RadialGradient, Shader, SpreadMode, StrokeDash, Transform,
+};
+
+use crate::brush::{Brush, Gradient, MappingMode, SpreadMethod, TileBrush, TileMode};
+use crate::color::Color;
+use crate::error::RenderResult;
+use crate::font;
+use crate::geometry::{FillRule, Geometry, PathBuilder, Point};
+use crate::image::Image;
+use crate::renderer::{
+    Canvas, Clip, Glyphs, LineCap, LineJoin, Opacity, OpacityMask, Path, RenderTransform, Renderer,
+};
+
+/// tiny-skia renderer, rasterizing to a pixmap without any
+/// system library
+pub struct TinySkiaRenderer {
+    // The target pixmap, followed by the layers of the elements
+    // being composited with an opacity or an opacity mask
+    layers: RefCell<Vec<Pixmap>>,
+    transform: Cell<Transform>,
+    clip: RefCell<Option<Rc<Mask>>>,
+}
+
+impl TinySkiaRenderer {
+    /// Returns a renderer drawing on the given pixmap, `transform`
+    /// maps the page coordinates to pixels
+    pub fn new(pixmap: Pixmap, transform: Transform) -> Self {
+        Self {
+            layers: RefCell::new(vec![pixmap]),
+            transform: Cell::new(transform),
+            clip: RefCell::new(None),
+        }
+    }
+
+    /// Returns the pixmap the renderer has drawn on
+    pub fn into_pixmap(self) -> Pixmap {
+        self.layers.into_inner().remove(0)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let layers = self.layers.borrow();
+        (layers[0].width(), layers[0].height())
+    }
+
+    // Wraps the rendering of an element, so that its transform and
+    // clip do not leak to its siblings
+    fn with_saved_state<F>(&self, f: F) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let transform = self.transform.get();
+        let clip = self.clip.borrow().clone();
+
+        let res = f();
+
+        self.transform.set(transform);
+        *self.clip.borrow_mut() = clip;
+
+        res
+    }
+
+    fn transform(&self, t: RenderTransform) {
+        self.transform
+            .set(self.transform.get().pre_concat(t.into()));
+    }
+
+    // Returns the bounds of the target in user space
+    fn user_bounds(&self) -> Option<tiny_skia::Rect> {
+        let (width, height) = self.size();
+        let inverse = self.transform.get().invert()?;
+
+        let mut corners = [
+            tiny_skia::Point::from_xy(0.0, 0.0),
+            tiny_skia::Point::from_xy(width as f32, 0.0),
+            tiny_skia::Point::from_xy(0.0, height as f32),
+            tiny_skia::Point::from_xy(width as f32, height as f32),
+        ];
+        inverse.map_points(&mut corners);
+
+        tiny_skia::Rect::from_points(&corners)
+    }
+
+    fn fill_path(&self, path: &tiny_skia::Path, fill_rule: FillRule, source: &Source) {
+        let clip = self.clip.borrow();
+        let mut layers = self.layers.borrow_mut();
+
+        if let Some(pixmap) = layers.last_mut() {
+            pixmap.fill_path(
+                path,
+                &source.paint(),
+                fill_rule.into(),
+                self.transform.get(),
+                clip.as_deref(),
+            );
+        }
+    }
+
+    fn stroke_path(&self, path: &tiny_skia::Path, stroke: &tiny_skia::Stroke, source: &Source) {
+        let clip = self.clip.borrow();
+        let mut layers = self.layers.borrow_mut();
+
+        if let Some(pixmap) = layers.last_mut() {
+            pixmap.stroke_path(
+                path,
+                &source.paint(),
+                stroke,
+                self.transform.get(),
+                clip.as_deref(),
+            );
+        }
+    }
+
+    // Returns the source painting the brush, `bounds` are the bounds
+    // of the geometry in user space. Returns None if the brush is
+    // not supported or does not paint anything.
+    fn brush_source(&self, brush: &Brush, bounds: tiny_skia::Rect) -> RenderResult<Option<Source>> {
+        match brush {
+            Brush::SolidColor(b) => Ok(Some(Source::Shader(Shader::SolidColor(skia_color(
+                &b.color, b.opacity,
+            ))))),
+            Brush::LinearGradient(b) => {
+                let g = &b.gradient;
+                let transform =
+                    brush_transform(Transform::identity(), g.mapping_mode, g.transform, bounds);
+
+                let shader = LinearGradient::new(
+                    b.start_point.into(),
+                    b.end_point.into(),
+                    gradient_stops(g),
+                    g.spread_method.into(),
+                    transform,
+                );
+
+                Ok(shader.map(Source::Shader))
+            }
+            Brush::RadialGradient(b) => {
+                if b.radius_x <= 0.0 || b.radius_y <= 0.0 {
+                    return Ok(None);
+                }
+
+                // tiny-skia only supports circular gradients, so the
+                // gradient is built in a space where the ellipse is a
+                // circle of radius RadiusX and then scaled vertically
+                let k = b.radius_x / b.radius_y;
+                let (o, c) = (b.gradient_origin, b.center);
+
+                let g = &b.gradient;
+                let transform = brush_transform(
+                    Transform::from_scale(1.0, (1.0 / k) as f32),
+                    g.mapping_mode,
+                    g.transform,
+                    bounds,
+                );
+
+                let shader = RadialGradient::new(
+                    Point::new(o.x, o.y * k).into(),
+                    Point::new(c.x, c.y * k).into(),
+                    b.radius_x as f32,
+                    gradient_stops(g),
+                    g.spread_method.into(),
+                    transform,
+                );
+
+                Ok(shader.map(Source::Shader))
+            }
+            Brush::Image(b) => {
+                let image = match b.image {
+                    Some(ref image) => image,
+                    None => return Ok(None),
+                };
+
+                let pixmap = match image_pixmap(image) {
+                    Some(pixmap) => pixmap,
+                    None => return Ok(None),
+                };
+
+                // The viewbox is in 1/96 inch, while the pixmap is
+                // in pixels
+                let (width, height) = image.size();
+                let (pixel_width, pixel_height) = image.pixel_size();
+
+                self.tile_source(&b.tile, bounds, |mut tile, transform| {
+                    let transform = transform.pre_scale(
+                        (width / f64::from(pixel_width)) as f32,
+                        (height / f64::from(pixel_height)) as f32,
+                    );
+
+                    let paint = PixmapPaint {
+                        quality: FilterQuality::Bilinear,
+                        ..PixmapPaint::default()
+                    };
+                    tile.draw_pixmap(0, 0, pixmap.as_ref(), &paint, transform, None);
+
+                    Ok(tile)
+                })
+            }
+            Brush::Visual(b) => self.tile_source(&b.tile, bounds, |tile, transform| {
+                let renderer = TinySkiaRenderer::new(tile, transform);
+                renderer.render_children(&b.visual)?;
+
+                Ok(renderer.into_pixmap())
+            }),
+        }
+    }
+
+    // Returns a pattern repeating the tile. The content is drawn by
+    // `draw` on the pixmap of a tile, with the given transform from
+    // the coordinate space of the viewbox.
+    fn tile_source<F>(
+        &self,
+        tile: &TileBrush,
+        bounds: tiny_skia::Rect,
+        draw: F,
+    ) -> RenderResult<Option<Source>>
+    where
+        F: FnOnce(Pixmap, Transform) -> RenderResult<Pixmap>,
+    {
+        let (vb, vp) = (tile.viewbox, tile.viewport);
+        if vb.width <= 0.0 || vb.height <= 0.0 || vp.width <= 0.0 || vp.height <= 0.0 {
+            return Ok(None);
+        }
+
+        // The tile is rendered at the resolution of the viewport on
+        // the device, so that the content is not pixelated
+        let viewport = Transform::from_row(
+            vp.width as f32,
+            0.0,
+            0.0,
+            vp.height as f32,
+            vp.x as f32,
+            vp.y as f32,
+        );
+        let to_user = brush_transform(viewport, tile.viewport_units, tile.transform, bounds);
+        let to_device = to_user.post_concat(self.transform.get());
+
+        let tile_width = to_device
+            .sx
+            .hypot(to_device.ky)
+            .ceil()
+            .clamp(1.0, MAX_TILE_SIZE);
+        let tile_height = to_device
+            .kx
+            .hypot(to_device.sy)
+            .ceil()
+            .clamp(1.0, MAX_TILE_SIZE);
+
+        let content = match Pixmap::new(tile_width as u32, tile_height as u32) {
+            Some(pixmap) => pixmap,
+            None => return Ok(None),
+        };
+
+        let viewbox = Transform::from_row(
+            tile_width / vb.width as f32,
+            0.0,
+            0.0,
+            tile_height / vb.height as f32,
+            0.0,
+            0.0,
+        )
+        .pre_translate(-vb.x as f32, -vb.y as f32);
+
+        let content = draw(content, viewbox)?;
+
+        let (columns, rows) = match tile.tile_mode {
+            TileMode::None | TileMode::Tile => (1, 1),
+            TileMode::FlipX => (2, 1),
+            TileMode::FlipY => (1, 2),
+            TileMode::FlipXY => (2, 2),
+        };
+
+        // tiny-skia cannot leave the area outside of a pattern empty,
+        // so a single tile gets a transparent border that is padded
+        let border = match tile.tile_mode {
+            TileMode::None => 1.0,
+            _ => 0.0,
+        };
+
+        let mut pixmap = match Pixmap::new(
+            (tile_width * columns as f32 + 2.0 * border) as u32,
+            (tile_height * rows as f32 + 2.0 * border) as u32,
+        ) {
+            Some(pixmap) => pixmap,
+            None => return Ok(None),
+        };
+
+        let paint = PixmapPaint {
+            opacity: tile.opacity as f32,
+            ..PixmapPaint::default()
+        };
+
+        // Odd columns and rows are mirrored images of the tile
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut cell = Transform::from_translate(
+                    border + column as f32 * tile_width,
+                    border + row as f32 * tile_height,
+                );
+                if column == 1 {
+                    cell = cell.pre_translate(tile_width, 0.0).pre_scale(-1.0, 1.0);
+                }
+                if row == 1 {
+                    cell = cell.pre_translate(0.0, tile_height).pre_scale(1.0, -1.0);
+                }
+
+                pixmap.draw_pixmap(0, 0, content.as_ref(), &paint, cell, None);
+            }
+        }
+
+        // Each tile of the pixmap is mapped to the viewport
+        let cell = Transform::from_row(
+            vp.width as f32 / tile_width,
+            0.0,
+            0.0,
+            vp.height as f32 / tile_height,
+            vp.x as f32,
+            vp.y as f32,
+        )
+        .pre_translate(-border, -border);
+        let transform = brush_transform(cell, tile.viewport_units, tile.transform, bounds);
+
+        if transform.invert().is_none() {
+            return Ok(None);
+        }
+
+        let spread_mode = match tile.tile_mode {
+            TileMode::None => SpreadMode::Pad,
+            _ => SpreadMode::Repeat,
+        };
+
+        Ok(Some(Source::Tile {
+            pixmap,
+            spread_mode,
+            transform,
+        }))
+    }
+
+    // Intersects the clip region with the clip geometry, in the
+    // coordinate space of the element
+    fn set_clip(&self, clip: &Option<Clip>) {
+        if let Some(Clip(ref geometry)) = clip {
+            let (width, height) = self.size();
+            let current = self.clip.borrow().clone();
+
+            let mask = match (current, build_path(geometry, false)) {
+                (Some(current), Some(path)) => {
+                    let mut mask = current.as_ref().clone();
+                    mask.intersect_path(
+                        &path,
+                        geometry.fill_rule.into(),
+                        true,
+                        self.transform.get(),
+                    );
+                    Some(mask)
+                }
+                (None, Some(path)) => Mask::new(width, height).map(|mut mask| {
+                    mask.fill_path(&path, geometry.fill_rule.into(), true, self.transform.get());
+                    mask
+                }),
+                // An empty clip geometry hides everything
+                (_, None) => Mask::new(width, height),
+            };
+
+            *self.clip.borrow_mut() = mask.map(Rc::new);
+        }
+    }
+
+    // Renders the content of an element with `draw`, composited with
+    // the opacity and the opacity mask of the element. The mask brush
+    // is mapped to the bounds of the element, or to the bounds of
+    // the target when the element has no geometry of its own.
+    fn with_opacity<F>(
+        &self,
+        opacity: Option<Opacity>,
+        opacity_mask: &Option<OpacityMask>,
+        bounds: Option<&Geometry>,
+        draw: F,
+    ) -> RenderResult<()>
+    where
+        F: FnOnce() -> RenderResult<()>,
+    {
+        let opacity = opacity.map_or(1.0, |o| o.0);
+
+        if opacity >= 1.0 && opacity_mask.is_none() {
+            return draw();
+        }
+
+        let (width, height) = self.size();
+        let layer = match Pixmap::new(width, height) {
+            Some(layer) => layer,
+            None => return draw(),
+        };
+
+        self.layers.borrow_mut().push(layer);
+        let res = draw();
+        let content = self.layers.borrow_mut().pop();
+
+        res?;
+
+        // An unsupported mask brush is ignored
+        let mask = match opacity_mask {
+            Some(OpacityMask(ref brush)) => self.opacity_mask(brush, bounds)?,
+            None => None,
+        };
+
+        let paint = PixmapPaint {
+            opacity: opacity as f32,
+            ..PixmapPaint::default()
+        };
+
+        let mut layers = self.layers.borrow_mut();
+
+        if let (Some(pixmap), Some(content)) = (layers.last_mut(), content) {
+            pixmap.draw_pixmap(
+                0,
+                0,
+                content.as_ref(),
+                &paint,
+                Transform::identity(),
+                mask.as_ref(),
+            );
+        }
+
+        Ok(())
+    }
+
+    // Returns the mask with the alpha of the brush painted over the
+    // whole target
+    fn opacity_mask(&self, brush: &Brush, bounds: Option<&Geometry>) -> RenderResult<Option<Mask>> {
+        let target = match self.user_bounds() {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+
+        let bounds = match bounds {
+            Some(geometry) => match build_path(geometry, false) {
+                Some(path) => path.bounds(),
+                None => return Ok(None),
+            },
+            None => target,
+        };
+
+        let source = match self.brush_source(brush, bounds)? {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+
+        let (width, height) = self.size();
+        let mut pixmap = match Pixmap::new(width, height) {
+            Some(pixmap) => pixmap,
+            None => return Ok(None),
+        };
+
+        pixmap.fill_rect(target, &source.paint(), self.transform.get(), None);
+
+        Ok(Some(Mask::from_pixmap(pixmap.as_ref(), MaskType::Alpha)))
+    }
+}
+
+impl Renderer for TinySkiaRenderer {
+    fn render_canvas(&self, canvas: &Canvas) -> RenderResult<()> {
+        self.with_saved_state(|| {
+            if let Some(t) = canvas.render_transform {
+                self.transform(t)
+            }
+
+            self.set_clip(&canvas.clip);
+
+            self.with_opacity(canvas.opacity, &canvas.opacity_mask, None, || {
+                self.render_children(&canvas.children)
+            })
+        })
+    }
+
+    fn render_glyphs(&self, glyphs: &Glyphs) -> RenderResult<()> {
+        let fill = match glyphs.fill {
+            Some(ref fill) => fill,
+            None => return Ok(()),
+        };
+
+        let outline = match font::outline(glyphs) {
+            Some(outline) => outline,
+            None => return Ok(()),
+        };
+
+        self.with_saved_state(|| {
+            if let Some(t) = glyphs.render_transform {
+                self.transform(t)
+            }
+
+            self.set_clip(&glyphs.clip);
+
+            let opacity_mask = &glyphs.opacity_mask;
+            self.with_opacity(glyphs.opacity, opacity_mask, Some(&outline), || {
+                let path = match build_path(&outline, false) {
+                    Some(path) => path,
+                    None => return Ok(()),
+                };
+
+                let source = match self.brush_source(&fill.0, path.bounds())? {
+                    Some(source) => source,
+                    None => return Ok(()),
+                };
+
+                self.fill_path(&path, outline.fill_rule, &source);
+
+                // The bold simulation is emulated by stroking the outline
+                if let Some(width) = font::bold_stroke_width(glyphs) {
+                    let stroke = tiny_skia::Stroke {
+                        width: width as f32,
+                        line_join: tiny_skia::LineJoin::Round,
+                        ..tiny_skia::Stroke::default()
+                    };
+
+                    self.stroke_path(&path, &stroke, &source);
+                }
+
+                Ok(())
+            })
+        })
+    }
+
+    fn render_path(&self, path: &Path) -> RenderResult<()> {
+        let data = match path.data {
+            Some(ref data) => data,
+            None => return Ok(()),
+        };
+
+        self.with_saved_state(|| {
+            if let Some(t) = path.render_transform {
+                self.transform(t)
+            }
+
+            self.set_clip(&path.clip);
+
+            self.with_opacity(path.opacity, &path.opacity_mask, Some(data), || {
+                if let Some(ref fill) = path.fill {
+                    if let Some(p) = build_path(data, false) {
+                        if let Some(source) = self.brush_source(&fill.0, p.bounds())? {
+                            self.fill_path(&p, data.fill_rule, &source);
+                        }
+                    }
+                }
+
+                if let Some(ref stroke) = path.stroke {
+                    let style = stroke_style(path);
+
+                    // A zero width is a hairline for tiny-skia
+                    if style.width <= 0.0 {
+                        return Ok(());
+                    }
+
+                    if let Some(p) = build_path(data, true) {
+                        if let Some(source) = self.brush_source(&stroke.0, p.bounds())? {
+                            self.stroke_path(&p, &style, &source);
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+        })
+    }
+}
+
+impl std::fmt::Debug for TinySkiaRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TinySkiaRenderer")
+            .field("size", &self.size())
+            .field("transform", &self.transform.get())
+            .finish()
+    }
+}
+
+// The paint source of a brush. The pixmap of a tile brush is owned
+// by the source, since the pattern shader borrows it.
+enum Source {
+    Shader(Shader<'static>),
+    Tile {
+        pixmap: Pixmap,
+        spread_mode: SpreadMode,
+        transform: Transform,
+    },
+}
+
+impl Source {
+    fn paint(&self) -> Paint<'_> {
+        let shader = match self {
+            Source::Shader(shader) => shader.clone(),
+            Source::Tile {
+                pixmap,
+                spread_mode,
+                transform,
+            } => Pattern::new(
+                pixmap.as_ref(),
+                *spread_mode,
+                FilterQuality::Bilinear,
+                1.0,
+                *transform,
+            ),
+        };
+
+        Paint {
+            shader,
+            anti_alias: true,
+            ..Paint::default()
+        }
+    }
+}
+
+// Maximum size in pixels of the pixmap used to render a tile
+const MAX_TILE_SIZE: f32 = 4096.0;
+
+// Returns the transform mapping the brush space to user space, given
+// the transform that maps the pattern space to the brush space
+fn brush_transform(
+    transform: Transform,
+    mapping_mode: MappingMode,
+    brush_transform: Option<RenderTransform>,
+    bounds: tiny_skia::Rect,
+) -> Transform {
+    let mut transform = transform;
+
+    // Relative coordinates are mapped to the bounding box of
+    // the geometry, before applying the brush transform
+    if mapping_mode == MappingMode::RelativeToBoundingBox {
+        let bbox = Transform::from_row(
+            bounds.width(),
+            0.0,
+            0.0,
+            bounds.height(),
+            bounds.x(),
+            bounds.y(),
+        );
+        transform = transform.post_concat(bbox);
+    }
+
+    if let Some(t) = brush_transform {
+        transform = transform.post_concat(t.into());
+    }
+
+    transform
+}
+
+// Returns the path of the geometry, in the coordinate space of the
+// element. The geometry transform only affects the coordinates of
+// the path, not the stroke.
+fn build_path(geometry: &Geometry, stroke: bool) -> Option<tiny_skia::Path> {
+    let mut builder = SkiaPathBuilder(tiny_skia::PathBuilder::new());
+    if stroke {
+        geometry.build_stroke(&mut builder);
+    } else {
+        geometry.build_fill(&mut builder);
+    }
+
+    let path = builder.0.finish()?;
+
+    match geometry.transform {
+        Some(t) => path.transform(t.into()),
+        None => Some(path),
+    }
+}
+
+fn stroke_style(path: &Path) -> tiny_skia::Stroke {
+    let thickness = path.stroke_thickness.unwrap_or_default().0;
+
+    // tiny-skia does not support different caps for the start and
+    // the end of a line, nor triangle caps
+    let cap = path.line_cap();
+    let join = path.stroke_line_join.map(|j| j.0).unwrap_or_default();
+    let miter_limit = path.stroke_miter_limit.unwrap_or_default();
+
+    let mut stroke = tiny_skia::Stroke {
+        width: thickness as f32,
+        miter_limit: miter_limit.0 as f32,
+        line_cap: match cap {
+            LineCap::Flat => tiny_skia::LineCap::Butt,
+            LineCap::Round | LineCap::Triangle => tiny_skia::LineCap::Round,
+            LineCap::Square => tiny_skia::LineCap::Square,
+        },
+        line_join: match join {
+            LineJoin::Miter => tiny_skia::LineJoin::Miter,
+            LineJoin::Bevel => tiny_skia::LineJoin::Bevel,
+            LineJoin::Round => tiny_skia::LineJoin::Round,
+        },
+        dash: None,
+    };
+
+    // Dashes are expressed in multiples of the stroke thickness, an
+    // odd number of dashes is repeated to get pairs of on and off
+    if let Some(ref dashes) = path.stroke_dash_array {
+        if !dashes.0.is_empty() && dashes.0.iter().any(|&d| d > 0.0) {
+            let mut dashes = dashes
+                .0
+                .iter()
+                .map(|d| (d * thickness) as f32)
+                .collect::<Vec<_>>();
+            if dashes.len() % 2 == 1 {
+                dashes.extend_from_within(..);
+            }
+
+            let offset = path.stroke_dash_offset.map(|o| o.0).unwrap_or_default();
+            stroke.dash = StrokeDash::new(dashes, (offset * thickness) as f32);
+        }
+    }
+
+    stroke
+}
+
+fn skia_color(color: &Color, opacity: f64) -> tiny_skia::Color {
+    let (r, g, b, a) = color.to_rgba();
+    let a = (a * opacity).clamp(0.0, 1.0);
+
+    tiny_skia::Color::from_rgba(r as f32, g as f32, b as f32, a as f32)
+        .unwrap_or(tiny_skia::Color::TRANSPARENT)
+}
+
+fn gradient_stops(gradient: &Gradient) -> Vec<GradientStop> {
+    gradient
+        .color_stops()
+        .iter()
+        .map(|(offset, color)| {
+            GradientStop::new(*offset as f32, skia_color(color, gradient.opacity))
+        })
+        .collect()
+}
+
+// Converts the image to a pixmap, with premultiplied alpha
+fn image_pixmap(image: &Image) -> Option<Pixmap> {
+    let (width, height) = image.pixel_size();
+
+    let data = image
+        .pixels()
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let a = u32::from(p[3]);
+            let premultiply = |c: u8| ((u32::from(c) * a + 127) / 255) as u8;
+            [
+                premultiply(p[0]),
+                premultiply(p[1]),
+                premultiply(p[2]),
+                p[3],
+            ]
+        })
+        .collect();
+
+    Pixmap::from_vec(data, IntSize::from_wh(width, height)?)
+}
+
+struct SkiaPathBuilder(tiny_skia::PathBuilder);
+
+impl PathBuilder for SkiaPathBuilder {
+    fn move_to(&mut self, p: Point) {
+        self.0.move_to(p.x as f32, p.y as f32);
+    }
+
+    fn line_to(&mut self, p: Point) {
+        self.0.line_to(p.x as f32, p.y as f32);
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.0.cubic_to(
+            c1.x as f32,
+            c1.y as f32,
+            c2.x as f32,
+            c2.y as f32,
+            p.x as f32,
+            p.y as f32,
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+impl From<Point> for tiny_skia::Point {
+    #[inline]
+    fn from(p: Point) -> Self {
+        Self::from_xy(p.x as f32, p.y as f32)
+    }
+}
+
+impl From<RenderTransform> for Transform {
+    #[inline]
+    fn from(t: RenderTransform) -> Self {
+        Self::from_row(
+            t.xx as f32,
+            t.yx as f32,
+            t.xy as f32,
+            t.yy as f32,
+            t.x0 as f32,
+            t.y0 as f32,
+        )
+    }
+}
+
+impl From<FillRule> for tiny_skia::FillRule {
+    #[inline]
+    fn from(r: FillRule) -> Self {
+        match r {
+            FillRule::EvenOdd => Self::EvenOdd,
+            FillRule::NonZero => Self::Winding,
+        }
+    }
+}
+
+impl From<SpreadMethod> for SpreadMode {
+    #[inline]
+    fn from(s: SpreadMethod) -> Self {
+        match s {
+            SpreadMethod::Pad => Self::Pad,
+            SpreadMethod::Reflect => Self::Reflect,
+            SpreadMethod::Repeat => Self::Repeat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parts::FixedPage;
+    use crate::renderer::RenderNode;
+    use std::sync::Arc;
+
+    const SIZE: u32 = 20;
+
+    fn page(content: &str) -> RenderNode {
+        let xml = format!(
+            r#"<FixedPage xmlns="http://schemas.microsoft.com/xps/2005/06" Width="20" Height="20">{}</FixedPage>"#,
+            content
+        );
+
+        FixedPage::parse(&xml, &|_| unreachable!())
+            .unwrap()
+            .render_tree
+    }
+
+    fn render(tree: &RenderNode) -> Pixmap {
+        let renderer =
+            TinySkiaRenderer::new(Pixmap::new(SIZE, SIZE).unwrap(), Transform::identity());
+        renderer.render(tree).unwrap();
+
+        renderer.into_pixmap()
+    }
+
+    // Returns the premultiplied RGBA color of the pixel
+    fn pixel(pixmap: &Pixmap, x: u32, y: u32) -> (u8, u8, u8, u8) {
+        let p = pixmap.pixel(x, y).unwrap();
+        (p.red(), p.green(), p.blue(), p.alpha())
+    }
+
+    fn assert_near(a: (u8, u8, u8, u8), b: (u8, u8, u8, u8)) {
+        let near = |a: u8, b: u8| a.abs_diff(b) <= 2;
+        assert!(
+            near(a.0, b.0) && near(a.1, b.1) && near(a.2, b.2) && near(a.3, b.3),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    const CLEAR: (u8, u8, u8, u8) = (0, 0, 0, 0);
+    const RED: (u8, u8, u8, u8) = (255, 0, 0, 255);
+    const BLUE: (u8, u8, u8, u8) = (0, 0, 255, 255);
+
+    #[test]
+    fn fill_rectangle() {
+        let pixmap = render(&page(
+            r##"<Path Data="M 5,5 L 15,5 L 15,15 L 5,15 Z" Fill="#FF0000"/>"##,
+        ));
+
+        assert_eq!(pixel(&pixmap, 5, 5), RED);
+        assert_eq!(pixel(&pixmap, 14, 14), RED);
+        assert_eq!(pixel(&pixmap, 4, 10), CLEAR);
+        assert_eq!(pixel(&pixmap, 15, 10), CLEAR);
+    }
+
+    #[test]
+    fn stroke_line() {
+        let pixmap = render(&page(
+            r##"<Path Data="M 2,10 L 18,10" Stroke="#0000FF" StrokeThickness="4"/>"##,
+        ));
+
+        assert_eq!(pixel(&pixmap, 10, 8), BLUE);
+        assert_eq!(pixel(&pixmap, 10, 11), BLUE);
+        assert_eq!(pixel(&pixmap, 10, 6), CLEAR);
+        assert_eq!(pixel(&pixmap, 10, 12), CLEAR);
+
+        // Flat caps end at the end points
+        assert_eq!(pixel(&pixmap, 2, 10), BLUE);
+        assert_eq!(pixel(&pixmap, 1, 10), CLEAR);
+    }
+
+    #[test]
+    fn clip() {
+        let pixmap = render(&page(
+            r##"<Canvas Clip="M 0,0 L 10,0 L 10,20 L 0,20 Z">
+                  <Path Data="M 0,0 L 20,0 L 20,20 L 0,20 Z" Fill="#FF0000"
+                      Clip="M 0,0 L 20,0 L 20,10 L 0,10 Z"/>
+                </Canvas>
+                <Path Data="M 0,15 L 20,15 L 20,20 L 0,20 Z" Fill="#0000FF"/>"##,
+        ));
+
+        // The clips are intersected, and do not apply to the siblings
+        assert_eq!(pixel(&pixmap, 5, 5), RED);
+        assert_eq!(pixel(&pixmap, 15, 5), CLEAR);
+        assert_eq!(pixel(&pixmap, 5, 12), CLEAR);
+        assert_eq!(pixel(&pixmap, 15, 17), BLUE);
+    }
+
+    #[test]
+    fn opacity_group() {
+        let pixmap = render(&page(
+            r##"<Canvas Opacity="0.5">
+                  <Path Data="M 0,0 L 15,0 L 15,20 L 0,20 Z" Fill="#FF0000"/>
+                  <Path Data="M 5,0 L 20,0 L 20,20 L 5,20 Z" Fill="#0000FF"/>
+                </Canvas>"##,
+        ));
+
+        // The children are composited as a group, so the red path
+        // does not show through the blue one
+        assert_near(pixel(&pixmap, 2, 10), (128, 0, 0, 128));
+        assert_near(pixel(&pixmap, 10, 10), (0, 0, 128, 128));
+        assert_near(pixel(&pixmap, 18, 10), (0, 0, 128, 128));
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let pixmap = render(&page(
+            r##"<Path Data="M 0,0 L 20,0 L 20,20 L 0,20 Z">
+                  <Path.Fill>
+                    <LinearGradientBrush MappingMode="Absolute" StartPoint="0,0" EndPoint="20,0">
+                      <LinearGradientBrush.GradientStops>
+                        <GradientStop Color="#FF0000" Offset="0"/>
+                        <GradientStop Color="#0000FF" Offset="1"/>
+                      </LinearGradientBrush.GradientStops>
+                    </LinearGradientBrush>
+                  </Path.Fill>
+                </Path>"##,
+        ));
+
+        let (r0, _, b0, a0) = pixel(&pixmap, 0, 10);
+        let (r1, _, b1, _) = pixel(&pixmap, 10, 10);
+        let (r2, _, b2, a2) = pixel(&pixmap, 19, 10);
+
+        assert_eq!((a0, a2), (255, 255));
+        assert!(r0 > 240 && b0 < 15);
+        assert!(r1.abs_diff(b1) < 20 && r1 > 100);
+        assert!(r2 < 15 && b2 > 240);
+
+        // The gradient is horizontal
+        assert_eq!(pixel(&pixmap, 10, 0), pixel(&pixmap, 10, 19));
+    }
+
+    #[test]
+    fn image_tile_none() {
+        let mut tree = page(
+            r#"<Path Data="M 0,0 L 20,0 L 20,20 L 0,20 Z">
+                 <Path.Fill>
+                   <ImageBrush ImageSource="image.png" Viewbox="0,0,2,2" Viewport="5,5,10,10"
+                       TileMode="None"/>
+                 </Path.Fill>
+               </Path>"#,
+        );
+
+        // A 2x2 image, red on the left and blue on the right
+        let image = Image::new(
+            2,
+            2,
+            (96.0, 96.0),
+            vec![
+                255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 0, 255, 255,
+            ],
+        );
+
+        match tree {
+            RenderNode::Root(ref mut children) => match children[0] {
+                RenderNode::Path(ref mut path) => match path.fill {
+                    Some(crate::renderer::Fill(Brush::Image(ref mut b))) => {
+                        b.image = Some(Arc::new(image))
+                    }
+                    _ => panic!("no image brush"),
+                },
+                _ => panic!("no path"),
+            },
+            _ => unreachable!(),
+        }
+
+        let pixmap = render(&tree);
+
+        // The image is stretched to the viewport, and not repeated
+        assert_near(pixel(&pixmap, 6, 10), RED);
+        assert_near(pixel(&pixmap, 13, 10), BLUE);
+        assert_eq!(pixel(&pixmap, 2, 10), CLEAR);
+        assert_eq!(pixel(&pixmap, 17, 10), CLEAR);
+        assert_eq!(pixel(&pixmap, 10, 2), CLEAR);
+        assert_eq!(pixel(&pixmap, 10, 17), CLEAR);
+    }
+}
@@ -1,11 +1,25 @@
-use log::debug;
-use std::path::Path;
-
-use crate::archive::Archive;
-use crate::error::Result;
-use crate::parts::{DocumentStructure, FixedDocument, FixedDocumentSequence, FixedPage};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::archive::{has_scheme, resolve_part_name, resolve_uri, Archive, ReadSeek};
+use crate::brush::Brush;
+use crate::color::{Color, ColorSpace};
+use crate::content_types::ContentTypes;
+use crate::error::{ParseError, ParseResult, Result};
+use crate::font::Font;
+use crate::image::Image;
+use crate::parts::{
+    DocumentStructure, FixedDocument, FixedDocumentSequence, FixedPage, FontPart, ImagePart,
+    RemoteResourceDictionary,
+};
 use crate::relationships::{DocumentRelationships, PackageRelationships};
-use crate::renderer::Renderer;
+use crate::renderer::{Fill, NavigateUri, OpacityMask, RenderNode, Renderer, Stroke};
+use crate::resources::{Resource, Resources};
+use crate::text::{self, TextRun};
 
 /// The main XPS entry point
 #[derive(Debug)]
@@ -18,15 +32,43 @@ impl XPS {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         debug!("Loading XPS {}", path.as_ref().display());
 
-        // TODO: this loads and parses all parts. Loading everything
-        // upfront, including all the pages, is *very* slow on a
-        // large document when running a debug build, but it seems
-        // "fast enough" in release builds...
-        // Should we consider loading page content lazily? That opens
-        // the can of worms related to shared mutable access to the
-        // archive.
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Loads an XPS archive from an in-memory buffer. The data
+    /// is copied, since pages are loaded on demand.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_reader(Cursor::new(data.to_vec()))
+    }
+
+    /// Loads an XPS archive from a reader. The reader is kept
+    /// until the `XPS` is dropped, since pages are loaded on demand.
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self> {
+        // Only the documents are parsed here, the content of the pages
+        // is parsed the first time it is needed
+
+        // The pages share the archive with each other, to load their
+        // content on demand
+        let mut archive = Archive::new(Box::new(reader) as Box<dyn ReadSeek>)?;
+
+        let content_types = match archive
+            .get_as_string("[Content_Types].xml")
+            .and_then(|s| ContentTypes::parse(&s))
+        {
+            Ok(content_types) => content_types,
+            Err(e) => {
+                warn!("Failed to load the content types: {}", e);
+                ContentTypes::default()
+            }
+        };
+
+        let package = Arc::new(Package {
+            archive: Mutex::new(archive),
+            content_types,
+            parts: Mutex::new(PartCache::default()),
+        });
 
-        let mut archive = Archive::new(path)?;
+        let mut archive = package.archive.lock().unwrap();
 
         let rels = archive
             .get_as_string("_rels/.rels")
@@ -61,10 +103,19 @@ impl XPS {
                         path.pop();
                         path.push(structure);
 
-                        let doc_structure = archive
-                            .get_as_string(path)
+                        let mut doc_structure = archive
+                            .get_as_string(&path)
                             .and_then(|s| DocumentStructure::parse(&s))?;
 
+                        // The targets are relative to the structure part
+                        if let Some(ref mut outline) = doc_structure.outline {
+                            for entry in outline.entries.iter_mut() {
+                                if let Some(ref mut target) = entry.target {
+                                    *target = resolve_uri(&path, target);
+                                }
+                            }
+                        }
+
                         doc.outline = doc_structure.outline;
                     }
                 }
@@ -79,16 +130,13 @@ impl XPS {
                     path.pop();
                     path.push(source);
 
-                    let fixed_page = archive
-                        .get_as_string(path)
-                        .and_then(|s| FixedPage::parse(&s))?;
-
                     doc.pages.push(Page {
-                        width: fixed_page.width.unwrap_or(p.width),
-                        height: fixed_page.height.unwrap_or(p.height),
-                        name: fixed_page.name,
-                        render_tree: fixed_page.render_tree,
+                        package: package.clone(),
+                        path,
+                        width: p.width,
+                        height: p.height,
                         links: p.links,
+                        content: OnceLock::new(),
                     });
                 }
 
@@ -107,6 +155,397 @@ impl XPS {
     }
 }
 
+type PackageArchive = Archive<Box<dyn ReadSeek>>;
+
+// The archive and the parts loaded from it, shared by all the pages.
+// The archive is only locked while reading a part, not across calls
+// to the renderer.
+struct Package {
+    archive: Mutex<PackageArchive>,
+    content_types: ContentTypes,
+    parts: Mutex<PartCache>,
+}
+
+impl Package {
+    // Parses the page with the given part name and loads the parts
+    // it uses
+    fn load_page(&self, path: &Path) -> Result<FixedPage> {
+        debug!("Loading page {}", path.display());
+
+        let content = self.archive.lock().unwrap().get_as_string(path)?;
+
+        let loader = |uri: &str| {
+            load_dictionary(
+                &mut self.archive.lock().unwrap(),
+                path,
+                uri,
+                &mut self.parts.lock().unwrap().dictionaries,
+            )
+        };
+
+        let mut fixed_page = FixedPage::parse(&content, &loader)?;
+
+        load_parts(
+            &mut self.archive.lock().unwrap(),
+            &self.content_types,
+            path,
+            &mut fixed_page.render_tree,
+            &mut self.parts.lock().unwrap(),
+        );
+
+        Ok(fixed_page)
+    }
+}
+
+impl std::fmt::Debug for Package {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Package")
+            .field("archive", &self.archive)
+            .finish()
+    }
+}
+
+// Fonts, images and remote resource dictionaries are shared by all
+// the pages that use them. Parts that failed to load are cached as None,
+// dictionaries with the cause of the failure.
+#[derive(Default)]
+struct PartCache {
+    fonts: HashMap<(PathBuf, u32), Option<Arc<Font>>>,
+    images: HashMap<PathBuf, Option<Arc<Image>>>,
+    profiles: HashMap<PathBuf, Option<ColorSpace>>,
+    dictionaries: HashMap<PathBuf, std::result::Result<Arc<Resources<'static>>, String>>,
+}
+
+fn load_dictionary(
+    archive: &mut PackageArchive,
+    page: &Path,
+    uri: &str,
+    dictionaries: &mut HashMap<PathBuf, std::result::Result<Arc<Resources<'static>>, String>>,
+) -> ParseResult<Arc<Resources<'static>>> {
+    let name = resolve_part_name(page, uri);
+
+    dictionaries
+        .entry(name.clone())
+        .or_insert_with(|| {
+            debug!("Loading resource dictionary {}", name.display());
+
+            match archive
+                .get_as_string(&name)
+                .and_then(|s| RemoteResourceDictionary::parse(&s))
+            {
+                Ok(mut dictionary) => {
+                    // The resources are used by the pages, the URIs
+                    // they contain are relative to the dictionary
+                    for resource in dictionary.resources_mut() {
+                        resolve_resource_uris(&name, resource);
+                    }
+
+                    Ok(Arc::new(dictionary))
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load resource dictionary {}: {}",
+                        name.display(),
+                        e
+                    );
+                    Err(e.to_string())
+                }
+            }
+        })
+        .clone()
+        .map_err(|e| ParseError::InvalidRemoteResourceDictionary(uri.to_string(), e))
+}
+
+// Makes the URIs of the images, fonts, color profiles and hyperlinks
+// of a resource absolute, resolving them against the part that
+// defines it
+fn resolve_resource_uris(part: &Path, resource: &mut Resource) {
+    match resource {
+        Resource::Brush(b) => resolve_brush_uris(part, b),
+        Resource::Visual(v) => resolve_node_uris(part, v),
+        Resource::Geometry(_) | Resource::Transform(_) => (),
+    }
+}
+
+fn resolve_node_uris(part: &Path, node: &mut RenderNode) {
+    match node {
+        RenderNode::Root(children) => {
+            for c in children.iter_mut() {
+                resolve_node_uris(part, c);
+            }
+        }
+        RenderNode::Canvas(canvas) => {
+            resolve_absolute_navigate_uri(part, &mut canvas.navigate_uri);
+
+            if let Some(OpacityMask(ref mut b)) = canvas.opacity_mask {
+                resolve_brush_uris(part, b);
+            }
+
+            for c in canvas.children.iter_mut() {
+                resolve_node_uris(part, c);
+            }
+        }
+        RenderNode::Glyphs(glyphs) => {
+            resolve_absolute_navigate_uri(part, &mut glyphs.navigate_uri);
+
+            glyphs.font_uri = absolute_uri(part, &glyphs.font_uri);
+
+            if let Some(Fill(ref mut b)) = glyphs.fill {
+                resolve_brush_uris(part, b);
+            }
+            if let Some(OpacityMask(ref mut b)) = glyphs.opacity_mask {
+                resolve_brush_uris(part, b);
+            }
+        }
+        RenderNode::Path(path) => {
+            resolve_absolute_navigate_uri(part, &mut path.navigate_uri);
+
+            if let Some(Fill(ref mut b)) = path.fill {
+                resolve_brush_uris(part, b);
+            }
+            if let Some(Stroke(ref mut b)) = path.stroke {
+                resolve_brush_uris(part, b);
+            }
+            if let Some(OpacityMask(ref mut b)) = path.opacity_mask {
+                resolve_brush_uris(part, b);
+            }
+        }
+    }
+}
+
+fn resolve_brush_uris(part: &Path, brush: &mut Brush) {
+    match brush {
+        Brush::Image(b) => b.image_source = absolute_uri(part, &b.image_source),
+        Brush::Visual(b) => {
+            for c in b.visual.iter_mut() {
+                resolve_node_uris(part, c);
+            }
+        }
+        _ => {
+            for color in brush.colors_mut() {
+                if let Color::Context {
+                    ref mut profile, ..
+                } = color
+                {
+                    *profile = absolute_uri(part, profile);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_absolute_navigate_uri(part: &Path, navigate_uri: &mut Option<NavigateUri>) {
+    if let Some(NavigateUri(ref mut uri)) = navigate_uri {
+        *uri = absolute_uri(part, uri);
+    }
+}
+
+// Resolves the URI against the part, as an absolute part name
+// that is not resolved again against the page
+fn absolute_uri(part: &Path, uri: &str) -> String {
+    if has_scheme(uri) {
+        String::from(uri)
+    } else {
+        format!("/{}", resolve_uri(part, uri))
+    }
+}
+
+// Loads the fonts and images used by the elements of the render tree
+fn load_parts(
+    archive: &mut PackageArchive,
+    content_types: &ContentTypes,
+    page: &Path,
+    node: &mut RenderNode,
+    cache: &mut PartCache,
+) {
+    match node {
+        RenderNode::Root(children) => {
+            for c in children.iter_mut() {
+                load_parts(archive, content_types, page, c, cache);
+            }
+        }
+        RenderNode::Canvas(canvas) => {
+            resolve_navigate_uri(page, &mut canvas.navigate_uri);
+
+            if let Some(OpacityMask(ref mut b)) = canvas.opacity_mask {
+                load_brush(archive, content_types, page, b, cache);
+            }
+
+            for c in canvas.children.iter_mut() {
+                load_parts(archive, content_types, page, c, cache);
+            }
+        }
+        RenderNode::Glyphs(glyphs) => {
+            resolve_navigate_uri(page, &mut glyphs.navigate_uri);
+
+            glyphs.font = load_font(
+                archive,
+                content_types,
+                page,
+                &glyphs.font_uri,
+                &mut cache.fonts,
+            );
+
+            if let Some(Fill(ref mut b)) = glyphs.fill {
+                load_brush(archive, content_types, page, b, cache);
+            }
+            if let Some(OpacityMask(ref mut b)) = glyphs.opacity_mask {
+                load_brush(archive, content_types, page, b, cache);
+            }
+        }
+        RenderNode::Path(path) => {
+            resolve_navigate_uri(page, &mut path.navigate_uri);
+
+            if let Some(Fill(ref mut b)) = path.fill {
+                load_brush(archive, content_types, page, b, cache);
+            }
+            if let Some(Stroke(ref mut b)) = path.stroke {
+                load_brush(archive, content_types, page, b, cache);
+            }
+            if let Some(OpacityMask(ref mut b)) = path.opacity_mask {
+                load_brush(archive, content_types, page, b, cache);
+            }
+        }
+    }
+}
+
+// Resolves the hyperlinks pointing inside the package against the page
+fn resolve_navigate_uri(page: &Path, navigate_uri: &mut Option<NavigateUri>) {
+    if let Some(NavigateUri(ref mut uri)) = navigate_uri {
+        *uri = resolve_uri(page, uri);
+    }
+}
+
+fn load_brush(
+    archive: &mut PackageArchive,
+    content_types: &ContentTypes,
+    page: &Path,
+    brush: &mut Brush,
+    cache: &mut PartCache,
+) {
+    match brush {
+        Brush::Image(b) => {
+            b.image = load_image(archive, page, &b.image_source, &mut cache.images);
+        }
+        Brush::Visual(b) => {
+            for c in b.visual.iter_mut() {
+                load_parts(archive, content_types, page, c, cache);
+            }
+        }
+        _ => {
+            for color in brush.colors_mut() {
+                load_color_profile(archive, page, color, &mut cache.profiles);
+            }
+        }
+    }
+}
+
+// Loads the ICC profile of a ContextColor to find its color space
+fn load_color_profile(
+    archive: &mut PackageArchive,
+    page: &Path,
+    color: &mut Color,
+    profiles: &mut HashMap<PathBuf, Option<ColorSpace>>,
+) {
+    if let Color::Context {
+        ref profile,
+        ref mut space,
+        ..
+    } = color
+    {
+        let name = resolve_part_name(page, profile);
+
+        *space = *profiles.entry(name.clone()).or_insert_with(|| {
+            debug!("Loading color profile {}", name.display());
+
+            match archive.get(&name) {
+                Ok(data) => match ColorSpace::from_profile(&data) {
+                    Ok(space) => Some(space),
+                    Err(e) => {
+                        warn!(
+                            "Unsupported color space {} of profile {}, colors are approximated",
+                            e,
+                            name.display()
+                        );
+                        None
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to load color profile {}: {}", name.display(), e);
+                    None
+                }
+            }
+        });
+    }
+}
+
+fn load_image(
+    archive: &mut PackageArchive,
+    page: &Path,
+    uri: &str,
+    images: &mut HashMap<PathBuf, Option<Arc<Image>>>,
+) -> Option<Arc<Image>> {
+    let name = resolve_part_name(page, uri);
+
+    images
+        .entry(name.clone())
+        .or_insert_with(|| {
+            debug!("Loading image {}", name.display());
+
+            match archive.get(&name).and_then(|data| ImagePart::parse(&data)) {
+                Ok(image) => Some(Arc::new(image)),
+                Err(e) => {
+                    warn!("Failed to load image {}: {}", name.display(), e);
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+fn load_font(
+    archive: &mut PackageArchive,
+    content_types: &ContentTypes,
+    page: &Path,
+    uri: &str,
+    fonts: &mut HashMap<(PathBuf, u32), Option<Arc<Font>>>,
+) -> Option<Arc<Font>> {
+    // The fragment is the index of the face in a font collection
+    let index = uri
+        .split('#')
+        .nth(1)
+        .and_then(|f| f.parse::<u32>().ok())
+        .unwrap_or_default();
+
+    let name = resolve_part_name(page, uri);
+
+    fonts
+        .entry((name.clone(), index))
+        .or_insert_with(|| {
+            debug!("Loading font {} (face {})", name.display(), index);
+
+            let data = match archive
+                .get(&name)
+                .and_then(|data| FontPart::parse(&name, content_types.get(&name), data))
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("Failed to load font {}: {}", name.display(), e);
+                    return None;
+                }
+            };
+
+            let font = Font::new(data, index).map(Arc::new);
+
+            if font.is_none() {
+                warn!("Invalid font {}", name.display());
+            }
+
+            font
+        })
+        .clone()
+}
+
 /// A document inside the XPS archive
 #[derive(Debug, Default)]
 pub struct Document {
@@ -124,6 +563,18 @@ impl Document {
     pub fn pages(&self) -> &[Page] {
         &self.pages
     }
+
+    /// Returns the text of the pages, see `Page::text`. Pages are
+    /// separated by an empty line.
+    pub fn text(&self) -> Result<String> {
+        let pages = self
+            .pages
+            .iter()
+            .map(Page::text)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(pages.join("\n\n"))
+    }
 }
 
 /// Outline of a `Document`. The outline of a document is a tree
@@ -148,28 +599,237 @@ pub struct OutlineEntry {
     pub(crate) target: Option<String>,
 }
 
-use crate::renderer::RenderNode;
-
-/// A page in a `Document`
+/// A page in a `Document`. The content of the page is loaded the
+/// first time the page is rendered.
 #[derive(Debug)]
 pub struct Page {
-    pub(crate) width: f64,
-    pub(crate) height: f64,
-    pub(crate) name: Option<String>,
-    pub(crate) render_tree: RenderNode,
+    package: Arc<Package>,
+    pub(crate) path: PathBuf,
+    pub(crate) width: Option<f64>,
+    pub(crate) height: Option<f64>,
     pub(crate) links: Vec<String>,
+    content: OnceLock<FixedPage>,
 }
 
 impl Page {
-    /// Returns the size of the page
+    /// Returns the size of the page. The size given in the
+    /// `FixedDocument` is only a hint, used until the content of the
+    /// page is loaded. The content is loaded when the hint is missing.
     pub fn size(&self) -> (f64, f64) {
-        (self.width, self.height)
+        let content = match (self.content.get(), self.width, self.height) {
+            (Some(page), _, _) => Ok(page),
+            (None, Some(width), Some(height)) => return (width, height),
+            (None, _, _) => self.content(),
+        };
+
+        match content {
+            Ok(page) => (
+                page.width.or(self.width).unwrap_or_default(),
+                page.height.or(self.height).unwrap_or_default(),
+            ),
+            Err(e) => {
+                warn!("Failed to load page {}: {}", self.path.display(), e);
+                (
+                    self.width.unwrap_or_default(),
+                    self.height.unwrap_or_default(),
+                )
+            }
+        }
+    }
+
+    /// Loads the content of the page, which is otherwise loaded the
+    /// first time it is needed. Renderers load the page before asking
+    /// its size, so that the hint of the `FixedDocument` is not used.
+    pub fn load(&self) -> Result<()> {
+        self.content().map(|_| ())
     }
 
     /// Renders a page with the given `Renderer`
     pub fn render(&self, renderer: &impl Renderer) -> Result<()> {
-        renderer.render(&self.render_tree)?;
+        let page = self.content()?;
+
+        renderer.render(&page.render_tree)?;
 
         Ok(())
     }
+
+    /// Returns the text of the page in reading order, with spaces
+    /// and newlines inserted between the runs of text according to
+    /// their position
+    pub fn text(&self) -> Result<String> {
+        let page = self.content()?;
+
+        Ok(text::text(&page.render_tree))
+    }
+
+    /// Returns the runs of text of the page with their position,
+    /// in reading order
+    pub fn text_runs(&self) -> Result<Vec<TextRun>> {
+        let page = self.content()?;
+
+        Ok(text::text_runs(&page.render_tree))
+    }
+
+    // Returns the content of the page, parsing it on first use
+    fn content(&self) -> Result<&FixedPage> {
+        if let Some(page) = self.content.get() {
+            return Ok(page);
+        }
+
+        // Pages loaded concurrently by other threads are dropped
+        let page = self.package.load_page(&self.path)?;
+
+        Ok(self.content.get_or_init(|| page))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Builds an XPS archive with one document per entry of `documents`,
+    // each listing its pages as (width, height) in the FixedDocument
+    fn package(documents: &[&[(Option<f64>, Option<f64>)]]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+
+        let mut add = |name: &str, content: &str| {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        };
+
+        add(
+            "_rels/.rels",
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+                 <Relationship Type="http://schemas.microsoft.com/xps/2005/06/fixedrepresentation" Target="FixedDocumentSequence.fdseq" Id="R0"/>
+               </Relationships>"#,
+        );
+
+        let mut references = String::new();
+
+        for (i, pages) in documents.iter().enumerate() {
+            references.push_str(&format!(
+                r#"<DocumentReference Source="/Documents/{}/FixedDocument.fdoc"/>"#,
+                i + 1
+            ));
+
+            add(
+                &format!("Documents/{}/_rels/FixedDocument.fdoc.rels", i + 1),
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"/>"#,
+            );
+
+            let mut content = String::new();
+
+            for (j, (width, height)) in pages.iter().enumerate() {
+                let size = |name, value: &Option<f64>| {
+                    value.map_or(String::new(), |v| format!(r#" {}="{}""#, name, v))
+                };
+
+                content.push_str(&format!(
+                    r#"<PageContent Source="Pages/{}.fpage"{}{}/>"#,
+                    j + 1,
+                    size("Width", width),
+                    size("Height", height)
+                ));
+
+                add(
+                    &format!("Documents/{}/Pages/{}.fpage", i + 1, j + 1),
+                    r#"<FixedPage xmlns="http://schemas.microsoft.com/xps/2005/06" Width="300" Height="400"/>"#,
+                );
+            }
+
+            add(
+                &format!("Documents/{}/FixedDocument.fdoc", i + 1),
+                &format!(
+                    r#"<FixedDocument xmlns="http://schemas.microsoft.com/xps/2005/06">{}</FixedDocument>"#,
+                    content
+                ),
+            );
+        }
+
+        add(
+            "FixedDocumentSequence.fdseq",
+            &format!(
+                r#"<FixedDocumentSequence xmlns="http://schemas.microsoft.com/xps/2005/06">{}</FixedDocumentSequence>"#,
+                references
+            ),
+        );
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<XPS>();
+        assert_send_sync::<Document>();
+        assert_send_sync::<Page>();
+    }
+
+    #[test]
+    fn dictionary_uris() {
+        let dictionary = Path::new("Resources/Dictionaries/common.dict");
+        let page = Path::new("Documents/1/Pages/1.fpage");
+
+        let uri = absolute_uri(dictionary, "../Images/logo.png");
+        assert_eq!(uri, "/Resources/Images/logo.png");
+        assert_eq!(
+            resolve_part_name(page, &uri),
+            PathBuf::from("Resources/Images/logo.png")
+        );
+
+        let uri = absolute_uri(dictionary, "font.odttf#1");
+        assert_eq!(uri, "/Resources/Dictionaries/font.odttf#1");
+
+        let uri = absolute_uri(dictionary, "/Documents/1/Pages/2.fpage#top");
+        assert_eq!(resolve_uri(page, &uri), "Documents/1/Pages/2.fpage#top");
+
+        let uri = absolute_uri(dictionary, "http://example.com/");
+        assert_eq!(uri, "http://example.com/");
+    }
+
+    #[test]
+    fn page_size() {
+        let data = package(&[&[(Some(100.0), Some(200.0)), (Some(100.0), None)]]);
+        let xps = XPS::from_bytes(&data).unwrap();
+        let pages = xps.documents()[0].pages();
+
+        // The size in the FixedDocument does not load the page
+        assert_eq!(pages[0].size(), (100.0, 200.0));
+        assert!(pages[0].content.get().is_none());
+
+        // A missing hint loads the page, whose size replaces the hint
+        assert_eq!(pages[1].size(), (300.0, 400.0));
+        assert!(pages[1].content.get().is_some());
+
+        // Once the page is loaded its own size replaces the hint
+        pages[0].text().unwrap();
+        assert_eq!(pages[0].size(), (300.0, 400.0));
+    }
+
+    #[test]
+    fn from_memory() {
+        let data = package(&[&[(Some(10.0), Some(20.0)); 3], &[(None, None)]]);
+
+        let counts = |xps: &XPS| {
+            xps.documents()
+                .iter()
+                .map(|d| d.pages().len())
+                .collect::<Vec<_>>()
+        };
+
+        let xps = XPS::from_bytes(&data).unwrap();
+        assert_eq!(counts(&xps), [3, 1]);
+
+        let xps = XPS::from_reader(Cursor::new(data)).unwrap();
+        assert_eq!(counts(&xps), [3, 1]);
+
+        // The reader is kept to load the pages
+        let page = &xps.documents()[1].pages()[0];
+        assert_eq!(page.size(), (300.0, 400.0));
+
+        assert!(XPS::from_bytes(b"not a zip").is_err());
+    }
 }
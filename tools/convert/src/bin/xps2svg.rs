@@ -0,0 +1,5 @@
+use rxps_convert::Format;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    rxps_convert::main(Some(Format::Svg))
+}
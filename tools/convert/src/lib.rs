@@ -0,0 +1,427 @@
+//! Convert XPS documents to PNG, PDF or SVG
+
+use clap::{crate_version, App, Arg, ArgMatches};
+use env_logger::Env;
+use log::{info, warn};
+use rxps::{Page, SvgRenderer, XPS};
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Output format of the conversion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A PNG image for each page
+    Png,
+    /// A single PDF document
+    Pdf,
+    /// An SVG image for each page
+    Svg,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(Format::Png),
+            "pdf" => Ok(Format::Pdf),
+            "svg" => Ok(Format::Svg),
+            _ => Err(format!("Unknown output format {}", s)),
+        }
+    }
+}
+
+// How the pages are scaled and painted
+struct Options {
+    dpi: f64,
+    scale: f64,
+    width: Option<f64>,
+    height: Option<f64>,
+    background: Option<(f64, f64, f64, f64)>,
+}
+
+impl Options {
+    // Returns the scale of the page: XPS units are 1/96 of an inch,
+    // the page is fitted in the width and height when given
+    fn page_scale(&self, (width, height): (f64, f64)) -> f64 {
+        let fit = match (self.width, self.height) {
+            (Some(w), Some(h)) => Some((w / width).min(h / height)),
+            (Some(w), None) => Some(w / width),
+            (None, Some(h)) => Some(h / height),
+            (None, None) => None,
+        };
+
+        fit.unwrap_or(self.dpi / 96.0 * self.scale)
+    }
+}
+
+/// Runs the converter. When `format` is not given, it is taken from
+/// the --format option or from the extension of the output file.
+pub fn main(format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let mut app = App::new("rxps convert")
+        .version(crate_version!())
+        .about("convert xps documents")
+        .args_from_usage(
+            "--log-level=[LEVEL]  'Control verbosity of the logs'
+             --document=[N]       'Convert only the Nth document'
+             --pages=[RANGES]     'Pages to convert, e.g. 1-3,5,8-'
+             --dpi=[DPI]          'Resolution of the images (default 96)'
+             --scale=[SCALE]      'Scale factor of the pages'
+             --width=[WIDTH]      'Fit the pages in the given width'
+             --height=[HEIGHT]    'Fit the pages in the given height'
+             --background=[COLOR] 'Background color, as #RRGGBB or #AARRGGBB'
+             <FILE>               'XPS file'
+             <OUTPUT>             'Output file, numbered when there are more pages'
+            ",
+        );
+
+    if format.is_none() {
+        app = app.arg(Arg::from_usage(
+            "--format=[FORMAT] 'Output format: png, pdf or svg'",
+        ));
+    }
+
+    let matches = app.get_matches();
+
+    // FIXME: we do not handle invalid values
+    let log_level = matches.value_of("log-level").unwrap_or("info");
+    let _ = env_logger::Builder::from_env(Env::default().default_filter_or(log_level)).try_init();
+
+    let file = matches.value_of("FILE").expect("Specify XPS file");
+    let output = Path::new(matches.value_of("OUTPUT").expect("Specify output file"));
+
+    let format = match (format, matches.value_of("format")) {
+        (Some(format), _) => format,
+        (None, Some(format)) => format.parse()?,
+        (None, None) => output
+            .extension()
+            .and_then(|e| e.to_str())
+            .ok_or("Specify the output format")?
+            .parse()?,
+    };
+
+    let options = Options {
+        dpi: parse_size_arg(&matches, "dpi")?.unwrap_or(96.0),
+        scale: parse_size_arg(&matches, "scale")?.unwrap_or(1.0),
+        width: parse_size_arg(&matches, "width")?,
+        height: parse_size_arg(&matches, "height")?,
+        background: matches
+            .value_of("background")
+            .map(parse_color)
+            .transpose()?,
+    };
+
+    let xps = XPS::load(file)?;
+
+    info!("Loaded XPS file {}", file);
+
+    let documents = match parse_arg::<usize>(&matches, "document")? {
+        Some(n) if n >= 1 && n <= xps.documents().len() => vec![n - 1],
+        Some(n) => return Err(format!("Document {} not found", n).into()),
+        None => (0..xps.documents().len()).collect(),
+    };
+
+    let ranges = match matches.value_of("pages") {
+        Some(ranges) => parse_ranges(ranges)?,
+        None => vec![(1, None)],
+    };
+
+    // The selected page indices of each selected document
+    let selection = documents
+        .iter()
+        .map(|&d| {
+            let count = xps.documents()[d].pages().len();
+            let pages = (0..count)
+                .filter(|&i| {
+                    ranges
+                        .iter()
+                        .any(|&(first, last)| i + 1 >= first && last.iter().all(|&l| i < l))
+                })
+                .collect::<Vec<_>>();
+
+            (d, pages)
+        })
+        .collect::<Vec<_>>();
+
+    if format == Format::Pdf {
+        if options.background.is_some()
+            || ["dpi", "scale", "width", "height"]
+                .iter()
+                .any(|o| matches.is_present(o))
+        {
+            warn!("The size and background options are ignored for PDF");
+        }
+
+        write_pdf(&xps, &selection, output)?;
+
+        info!("Converted {} to {}", file, output.display());
+
+        return Ok(());
+    }
+
+    let count = selection.iter().map(|(_, p)| p.len()).sum::<usize>();
+    if count == 0 {
+        return Err("No pages to convert".into());
+    }
+
+    for (d, pages) in selection.iter() {
+        for &i in pages.iter() {
+            let page = &xps.documents()[*d].pages()[i];
+
+            // Number the files when converting more than a page
+            let path = match count {
+                1 => output.to_path_buf(),
+                _ if documents.len() > 1 => numbered_path(output, &format!("{}-{}", d + 1, i + 1)),
+                _ => numbered_path(output, &(i + 1).to_string()),
+            };
+
+            match format {
+                Format::Png => write_png(page, &options, &path)?,
+                Format::Svg => write_svg(page, &options, &path)?,
+                Format::Pdf => unreachable!(),
+            }
+
+            info!("Converted page {} to {}", i + 1, path.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "cairo")]
+fn write_pdf(
+    xps: &XPS,
+    selection: &[(usize, Vec<usize>)],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = rxps::PdfWriter::new(File::create(path)?)?;
+    for (d, pages) in selection.iter() {
+        writer.add_document(&xps.documents()[*d], pages);
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "cairo"))]
+fn write_pdf(_: &XPS, _: &[(usize, Vec<usize>)], _: &Path) -> Result<(), Box<dyn Error>> {
+    Err("PDF output needs the cairo feature".into())
+}
+
+#[cfg(feature = "cairo")]
+fn write_png(page: &Page, options: &Options, path: &Path) -> Result<(), Box<dyn Error>> {
+    page.load()?;
+    let size = page.size();
+    let scale = options.page_scale(size);
+    let width = (size.0 * scale).ceil() as i32;
+    let height = (size.1 * scale).ceil() as i32;
+
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)
+        .map_err(|s| format!("Failed to create a {}x{} image: {}", width, height, s))?;
+    let cr = cairo::Context::new(&surface);
+
+    if let Some((r, g, b, a)) = options.background {
+        cr.set_source_rgba(r, g, b, a);
+        cr.paint();
+    }
+
+    cr.scale(scale, scale);
+    page.render(&rxps::CairoRenderer::new(cr))?;
+
+    surface.write_to_png(&mut File::create(path)?)?;
+
+    Ok(())
+}
+
+// Without cairo, the pages are rasterized with tiny-skia
+#[cfg(not(feature = "cairo"))]
+fn write_png(page: &Page, options: &Options, path: &Path) -> Result<(), Box<dyn Error>> {
+    page.load()?;
+    let size = page.size();
+    let scale = options.page_scale(size);
+    let width = (size.0 * scale).ceil() as u32;
+    let height = (size.1 * scale).ceil() as u32;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("Failed to create a {}x{} image", width, height))?;
+
+    if let Some((r, g, b, a)) = options.background {
+        if let Some(color) = tiny_skia::Color::from_rgba(r as f32, g as f32, b as f32, a as f32) {
+            pixmap.fill(color);
+        }
+    }
+
+    let transform = tiny_skia::Transform::from_scale(scale as f32, scale as f32);
+    let renderer = rxps::TinySkiaRenderer::new(pixmap, transform);
+    page.render(&renderer)?;
+
+    renderer.into_pixmap().save_png(path)?;
+
+    Ok(())
+}
+
+fn write_svg(page: &Page, options: &Options, path: &Path) -> Result<(), Box<dyn Error>> {
+    page.load()?;
+    let size = page.size();
+    let scale = options.page_scale(size);
+
+    let mut renderer = SvgRenderer::new(size.0, size.1);
+    renderer.set_size(size.0 * scale, size.1 * scale);
+    if let Some((r, g, b, a)) = options.background {
+        renderer.set_background(r, g, b, a);
+    }
+
+    page.render(&renderer)?;
+
+    std::fs::write(path, renderer.into_svg())?;
+
+    Ok(())
+}
+
+fn parse_arg<T: FromStr>(matches: &ArgMatches<'_>, name: &str) -> Result<Option<T>, String> {
+    match matches.value_of(name) {
+        Some(value) => match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(format!("Invalid value {} for --{}", value, name)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_size_arg(matches: &ArgMatches<'_>, name: &str) -> Result<Option<f64>, String> {
+    matches
+        .value_of(name)
+        .map(|value| parse_size(name, value))
+        .transpose()
+}
+
+// Parses the value of a size option, which must be a positive number
+fn parse_size(name: &str, s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .ok_or_else(|| format!("Invalid value {} for --{}", s, name))
+}
+
+// Parses a list of page ranges, e.g. "1-3,5,8-", into the first
+// and the last page of each range, counting from 1
+fn parse_ranges(s: &str) -> Result<Vec<(usize, Option<usize>)>, String> {
+    let invalid = || format!("Invalid page range {}", s);
+    let page = |p: &str| p.trim().parse::<usize>().map_err(|_| invalid());
+
+    let mut ranges = Vec::new();
+
+    for range in s.split(',') {
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) if last.trim().is_empty() => (page(first)?, None),
+            Some((first, last)) => (page(first)?, Some(page(last)?)),
+            None => (page(range)?, Some(page(range)?)),
+        };
+
+        if first == 0 || last.is_some_and(|l| l < first) {
+            return Err(invalid());
+        }
+
+        ranges.push((first, last));
+    }
+
+    Ok(ranges)
+}
+
+// Parses a color in the XPS sRGB syntax, #RRGGBB or #AARRGGBB
+fn parse_color(s: &str) -> Result<(f64, f64, f64, f64), String> {
+    let invalid = || format!("Invalid color {}", s);
+
+    let hex = s
+        .strip_prefix('#')
+        .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(invalid)?;
+    let value = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    let c = |shift: u32| f64::from((value >> shift) & 0xff) / 255.0;
+
+    match hex.len() {
+        6 => Ok((c(16), c(8), c(0), 1.0)),
+        8 => Ok((c(16), c(8), c(0), c(24))),
+        _ => Err(invalid()),
+    }
+}
+
+// Inserts the number before the extension of the file name
+fn numbered_path(path: &Path, number: &str) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push("-");
+    name.push(number);
+
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            parse_ranges("1-3,5, 8-").unwrap(),
+            vec![(1, Some(3)), (5, Some(5)), (8, None)]
+        );
+        assert_eq!(parse_ranges("2-2").unwrap(), vec![(2, Some(2))]);
+
+        for s in &["", "0", "0-2", "3-1", "a", "1-b", "1,,2", "-3", "1-2-3"] {
+            assert!(parse_ranges(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("dpi", "300").unwrap(), 300.0);
+        assert_eq!(parse_size("scale", "0.5").unwrap(), 0.5);
+
+        for s in &["", "0", "-0", "-2", "inf", "NaN", "1e400", "a"] {
+            assert!(parse_size("width", s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff0000").unwrap(), (1.0, 0.0, 0.0, 1.0));
+        assert_eq!(parse_color("#00FF0000").unwrap(), (1.0, 0.0, 0.0, 0.0));
+        assert_eq!(parse_color("#80000000").unwrap().3, 128.0 / 255.0);
+
+        for s in &["ff0000", "#ff00", "#ff00000", "#gg0000", "#+f0000", "red"] {
+            assert!(parse_color(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn scale() {
+        let options = |width: Option<f64>, height: Option<f64>| Options {
+            dpi: 192.0,
+            scale: 0.5,
+            width,
+            height,
+            background: None,
+        };
+
+        assert_eq!(options(None, None).page_scale((100.0, 50.0)), 1.0);
+        assert_eq!(options(Some(50.0), None).page_scale((100.0, 50.0)), 0.5);
+        assert_eq!(options(None, Some(200.0)).page_scale((100.0, 50.0)), 4.0);
+
+        // The page is fitted in both dimensions
+        assert_eq!(
+            options(Some(300.0), Some(100.0)).page_scale((100.0, 50.0)),
+            2.0
+        );
+        assert_eq!(
+            options(Some(100.0), Some(300.0)).page_scale((100.0, 50.0)),
+            1.0
+        );
+    }
+}
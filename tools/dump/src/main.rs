@@ -2,6 +2,7 @@ use clap::{crate_version, App};
 use env_logger::Env;
 use log::info;
 use rxps::{CairoRenderer, XPS};
+use std::fs::File;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app = App::new("rxps dump")
@@ -9,6 +10,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .about("dump xps information")
         .args_from_usage(
             "--log-level=[LEVEL] 'Control verbosity of the logs'
+             --pdf=[OUTPUT]      'Convert the XPS file to PDF'
              [FILE]              'XPS file'
             ",
         );
@@ -41,9 +43,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(output) = matches.value_of("pdf") {
+        xps.write_pdf(File::create(output)?)?;
+
+        info!("Converted XPS file {} to {}", file, output);
+    }
+
     // Render a page for testing purposes
 
     let p = &xps.documents()[0].pages()[0];
+    p.load()?;
     let (w, h) = p.size();
     let s = cairo::ImageSurface::create(cairo::Format::ARgb32, w as i32, h as i32).unwrap();
     let cr = cairo::Context::new(&s);